@@ -0,0 +1,478 @@
+use std::{env, fs, process};
+
+use binhis::{
+    image::{
+        AdaptiveMethod, Adjustment, BinaryMorphology, Curve, GrayMorphology, HistogramTarget,
+        Image, Intensity, Levels, StructuringElement, ThresholdMode, MAX_DIMENSION,
+    },
+    pipeline::{Operation, Pipeline},
+    threshold::ThresholdMethod,
+};
+use image::{ImageFormat, ImageOutputFormat};
+
+const USAGE: &str =
+    "Usage: binhis-cli <input> <output> <operation> [parameters...] [+ <operation> ...]
+
+Operations:
+    stretch [rgb|y|l|v]          channels to remap, R, G and B independently by
+    equalize [rgb|y|l|v]         default, or Y of YCbCr, L* of Lab or V of HSV
+    stretch-clipped <low> <high> [rgb|y|l|v] [linked]
+                                 fractions of pixels in 0.0..=0.5 saturated to black
+                                 and white, linked channels share one input range
+    levels <input-black> <input-white> <gamma> <output-black> <output-white> [rgb|y|l|v]
+    curve <x>:<y> [<x>:<y> ...] [rgb|y|l|v]
+                                 spline through control points, levels in 0..=255
+    gamma <gamma> [rgb|y|l|v]    above 1 brightens the midtones
+    brightness-contrast <brightness> <contrast> [rgb|y|l|v]
+                                 brightness in -255..=255, contrast in -100..=100
+    log <strength> [rgb|y|l|v]   strength in 1..=1000
+    exp <strength> [rgb|y|l|v]
+    negative [rgb|y|l|v]
+    solarize <threshold> [rgb|y|l|v]
+    posterize <levels> [rgb|y|l|v]
+                                 levels in 2..=255
+    clahe <columns> <rows> <clip-limit> [rgb|y|l|v]
+                                 equalizes a grid of tiles, clip limit relative to
+                                 the mean bin count, 0 to disable clipping
+    match-reference <reference> [rgb|y|l|v]
+                                 matches the histogram of a reference image file
+    match-gaussian <mean> <deviation> [rgb|y|l|v]
+    match-exponential <rate> [rgb|y|l|v]
+    match-rayleigh <scale> [rgb|y|l|v]
+    threshold <low> <high>       white if any of R, G or B is in range
+    threshold-all <low> <high>   white if R, G and B all are in range
+    threshold-luminance <low> <high>
+    threshold-rgb <red-low> <red-high> <green-low> <green-high> <blue-low> <blue-high>
+                                 keeps each channel in its own range, color output
+    threshold-hsv <hue-low> <hue-high> <saturation-low> <saturation-high> <value-low> <value-high>
+                                 hue in 0..=360 wrapping if low > high, others in 0..=1
+    threshold-lab <l*-low> <l*-high> <a*-low> <a*-high> <b*-low> <b*-high>
+    percent-black <percent>      fraction of pixels in 0.0..=1.0
+    mean-iterative
+    entropy
+    min-error
+    fuzzy-min-error
+    otsu
+    huang
+    intermodes
+    isodata
+    li
+    max-entropy
+    mean
+    minimum
+    moments
+    renyi-entropy
+    shanbhag
+    triangle
+    yen
+    multi-otsu <count>           number of thresholds, renders count + 1 gray levels
+    niblack <window> <k>
+    sauvola <window> <k> <r>
+    wolf <window> <k>
+    bernsen <window> <contrast-limit>
+    phansalkar <window> <k> <r>
+    erode <element>              dark pixels are the foreground, <element> is
+    dilate <element>             square, disk, cross or ball followed by a radius,
+    open <element>               rectangle followed by a horizontal and a vertical
+    close <element>              radius, or rows of 1 (foreground), 0 (background)
+                                 and . (either) separated by /, such as 010/111/010
+    top-hat <element>
+    black-hat <element>
+    gradient <element>
+    hit-or-miss <element>        only custom elements have background cells
+    skeleton <element>
+    thin
+    fill-holes
+    gray-erode <element> [rgb|y|l|v]
+    gray-dilate <element> [rgb|y|l|v]
+    gray-open <element> [rgb|y|l|v]
+                                 a ball or large rectangle estimates the background
+    gray-close <element> [rgb|y|l|v]
+    gray-top-hat <element> [rgb|y|l|v]
+    gray-black-hat <element> [rgb|y|l|v]
+                                 dark details on black, negate to flatten the background
+    gray-gradient <element> [rgb|y|l|v]
+
+Operations separated by + are applied one after another.
+The output format is picked from the output file extension (png, jpg, bmp, gif).";
+
+fn parse<T: std::str::FromStr>(args: &[String], index: usize, name: &str) -> Result<T, String> {
+    let value = args
+        .get(index)
+        .ok_or_else(|| format!("Missing parameter <{}>.", name))?;
+
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for <{}>: {}", name, value))
+}
+
+fn parse_intensity(params: &[String]) -> Result<Intensity, String> {
+    match params.first().map(String::as_str) {
+        None | Some("rgb") => Ok(Intensity::PerChannel),
+        Some("y") => Ok(Intensity::Luma),
+        Some("l") => Ok(Intensity::LabLightness),
+        Some("v") => Ok(Intensity::Value),
+        Some(value) => Err(format!("Invalid value for [channels]: {}", value)),
+    }
+}
+
+/// Structuring element at the start of `params` and the number of
+/// parameters it takes up.
+fn parse_element(params: &[String]) -> Result<(StructuringElement, usize), String> {
+    let shape = params.first().ok_or("Missing parameter <element>.")?;
+    let mut element = match StructuringElement::ALL
+        .into_iter()
+        .find(|element| element.name().eq_ignore_ascii_case(shape))
+    {
+        Some(element) => element,
+        None => {
+            return StructuringElement::parse(shape)
+                .map(|element| (element, 1))
+                .ok_or_else(|| format!("Invalid value for <element>: {}", shape))
+        }
+    };
+
+    let count = element.parameters().len();
+    for (i, name) in element.parameters().iter().enumerate() {
+        let value = parse(params, i + 1, name)?;
+        if value > MAX_DIMENSION {
+            return Err(format!(
+                "<{}> must be in range 0..={}.",
+                name, MAX_DIMENSION
+            ));
+        }
+        element = element.with_value(i, value);
+    }
+
+    Ok((element, count + 1))
+}
+
+fn parse_operation(operation: &str, params: &[String]) -> Result<Operation, String> {
+    let operation = match operation {
+        "stretch" => Operation::Stretch(parse_intensity(params)?),
+        "equalize" => Operation::Equalize(parse_intensity(params)?),
+        "stretch-clipped" => {
+            let low: f32 = parse(params, 0, "low")?;
+            let high: f32 = parse(params, 1, "high")?;
+            if !(0.0..=0.5).contains(&low) || !(0.0..=0.5).contains(&high) {
+                return Err("<low> and <high> must be in range 0.0..=0.5.".to_string());
+            }
+            let (intensity, linked) = match &params[2..] {
+                [rest @ .., linked] if linked == "linked" => (parse_intensity(rest)?, true),
+                rest => (parse_intensity(rest)?, false),
+            };
+            Operation::StretchClipped {
+                clip: (low, high),
+                intensity,
+                linked,
+            }
+        }
+        "levels" => {
+            let input = (
+                parse(params, 0, "input-black")?,
+                parse(params, 1, "input-white")?,
+            );
+            let gamma: f32 = parse(params, 2, "gamma")?;
+            let output = (
+                parse(params, 3, "output-black")?,
+                parse(params, 4, "output-white")?,
+            );
+            if input.0 >= input.1 {
+                return Err("<input-black> must be less than <input-white>.".to_string());
+            }
+            if gamma <= 0.0 {
+                return Err("<gamma> must be greater than 0.".to_string());
+            }
+            Operation::Levels {
+                levels: Levels {
+                    input,
+                    gamma,
+                    output,
+                },
+                intensity: parse_intensity(&params[5..])?,
+            }
+        }
+        "curve" => {
+            let count = params
+                .iter()
+                .take_while(|param| param.contains(':'))
+                .count();
+            if count == 0 {
+                return Err("Missing parameter <x>:<y>.".to_string());
+            }
+            let points = params[..count]
+                .iter()
+                .map(|point| {
+                    let (x, y) = point.split_once(':').unwrap();
+                    match (x.parse(), y.parse()) {
+                        (Ok(x), Ok(y)) => Ok((x, y)),
+                        _ => Err(format!("Invalid value for <x>:<y>: {}", point)),
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Operation::Curve {
+                curve: Curve::new(points),
+                intensity: parse_intensity(&params[count..])?,
+            }
+        }
+        "gamma" | "brightness-contrast" | "log" | "exp" | "negative" | "solarize" | "posterize" => {
+            let mut adjustment = match operation {
+                "gamma" => Adjustment::ALL[0],
+                "brightness-contrast" => Adjustment::ALL[1],
+                "log" => Adjustment::ALL[2],
+                "exp" => Adjustment::ALL[3],
+                "negative" => Adjustment::ALL[4],
+                "solarize" => Adjustment::ALL[5],
+                _ => Adjustment::ALL[6],
+            };
+            let count = adjustment.parameters().len();
+            for (i, parameter) in adjustment.parameters().iter().enumerate() {
+                let value: f32 = parse(params, i, parameter)?;
+                let (min, max) = adjustment.limits()[i];
+                if !(min..=max).contains(&value) {
+                    return Err(format!(
+                        "<{}> must be in range {}..={}.",
+                        parameter, min, max
+                    ));
+                }
+                adjustment = adjustment.with_value(i, value);
+            }
+            Operation::Adjust {
+                adjustment,
+                intensity: parse_intensity(&params[count..])?,
+            }
+        }
+        "clahe" => {
+            let columns: u32 = parse(params, 0, "columns")?;
+            let rows: u32 = parse(params, 1, "rows")?;
+            let clip_limit: f32 = parse(params, 2, "clip-limit")?;
+            if columns == 0 || rows == 0 {
+                return Err("<columns> and <rows> must be greater than 0.".to_string());
+            }
+            Operation::Clahe {
+                grid: (columns, rows),
+                clip_limit,
+                intensity: parse_intensity(params.get(3..).unwrap_or(&[]))?,
+            }
+        }
+        "match-reference" => {
+            let path: String = parse(params, 0, "reference")?;
+            let data =
+                fs::read(&path).map_err(|error| format!("Couldn't read {}: {}", path, error))?;
+            let reference = Image::new_with_data(data).map_err(|error| error.to_string())?;
+            Operation::MatchHistogram {
+                target: HistogramTarget::reference(&reference),
+                intensity: parse_intensity(&params[1..])?,
+            }
+        }
+        "match-gaussian" | "match-exponential" | "match-rayleigh" => {
+            let mut target = match operation {
+                "match-gaussian" => HistogramTarget::ALL[0].clone(),
+                "match-exponential" => HistogramTarget::ALL[1].clone(),
+                _ => HistogramTarget::ALL[2].clone(),
+            };
+            let count = target.parameters().len();
+            for (i, parameter) in target.parameters().iter().enumerate() {
+                let value: f32 = parse(params, i, parameter)?;
+                let (min, max) = target.limits()[i];
+                if !(min..=max).contains(&value) {
+                    return Err(format!(
+                        "<{}> must be in range {}..={}.",
+                        parameter, min, max
+                    ));
+                }
+                target = target.with_value(i, value);
+            }
+            Operation::MatchHistogram {
+                target,
+                intensity: parse_intensity(&params[count..])?,
+            }
+        }
+        "threshold"
+        | "threshold-all"
+        | "threshold-luminance"
+        | "threshold-rgb"
+        | "threshold-hsv"
+        | "threshold-lab" => {
+            let mut mode = match operation {
+                "threshold" => ThresholdMode::ALL[0],
+                "threshold-all" => ThresholdMode::ALL[1],
+                "threshold-luminance" => ThresholdMode::ALL[2],
+                "threshold-rgb" => ThresholdMode::ALL[3],
+                "threshold-hsv" => ThresholdMode::ALL[4],
+                _ => ThresholdMode::ALL[5],
+            };
+            let components = mode.components();
+            for (i, component) in components.iter().enumerate() {
+                let (low_name, high_name) = if components.len() == 1 {
+                    ("low".to_string(), "high".to_string())
+                } else {
+                    let component = component.to_lowercase();
+                    (format!("{}-low", component), format!("{}-high", component))
+                };
+                let low: f32 = parse(params, i * 2, &low_name)?;
+                let high: f32 = parse(params, i * 2 + 1, &high_name)?;
+                let (min, max) = mode.limits()[i];
+                if !(min..=max).contains(&low) || !(min..=max).contains(&high) {
+                    return Err(format!(
+                        "<{}> and <{}> must be in range {}..={}.",
+                        low_name, high_name, min, max
+                    ));
+                }
+                if low > high && !mode.wraps(i) {
+                    return Err(format!(
+                        "<{}> must not be greater than <{}>.",
+                        low_name, high_name
+                    ));
+                }
+                mode = mode.with_range(i, (low, high));
+            }
+            Operation::Threshold(mode)
+        }
+        "percent-black" => {
+            let percent: f32 = parse(params, 0, "percent")?;
+            if !(0.0..=1.0).contains(&percent) {
+                return Err("<percent> must be in range 0.0..=1.0.".to_string());
+            }
+            Operation::AutoThreshold(ThresholdMethod::PercentBlack(percent))
+        }
+        "mean-iterative" => Operation::AutoThreshold(ThresholdMethod::MeanIterative),
+        "entropy" => Operation::AutoThreshold(ThresholdMethod::Entropy),
+        "min-error" => Operation::AutoThreshold(ThresholdMethod::MinimumError),
+        "fuzzy-min-error" => Operation::AutoThreshold(ThresholdMethod::FuzzyMinimumError),
+        "otsu" => Operation::AutoThreshold(ThresholdMethod::Otsu),
+        "huang" => Operation::AutoThreshold(ThresholdMethod::Huang),
+        "intermodes" => Operation::AutoThreshold(ThresholdMethod::Intermodes),
+        "isodata" => Operation::AutoThreshold(ThresholdMethod::IsoData),
+        "li" => Operation::AutoThreshold(ThresholdMethod::Li),
+        "max-entropy" => Operation::AutoThreshold(ThresholdMethod::MaxEntropy),
+        "mean" => Operation::AutoThreshold(ThresholdMethod::Mean),
+        "minimum" => Operation::AutoThreshold(ThresholdMethod::Minimum),
+        "moments" => Operation::AutoThreshold(ThresholdMethod::Moments),
+        "renyi-entropy" => Operation::AutoThreshold(ThresholdMethod::RenyiEntropy),
+        "shanbhag" => Operation::AutoThreshold(ThresholdMethod::Shanbhag),
+        "triangle" => Operation::AutoThreshold(ThresholdMethod::Triangle),
+        "yen" => Operation::AutoThreshold(ThresholdMethod::Yen),
+        "multi-otsu" => {
+            let count: usize = parse(params, 0, "count")?;
+            if !(1..=255).contains(&count) {
+                return Err("<count> must be in range 1..=255.".to_string());
+            }
+            Operation::MultiOtsu(count)
+        }
+        "niblack" | "sauvola" | "wolf" | "bernsen" | "phansalkar" => {
+            let window = parse(params, 0, "window")?;
+            if !(1..=MAX_DIMENSION).contains(&window) {
+                return Err(format!("<window> must be in range 1..={}.", MAX_DIMENSION));
+            }
+            let method = match operation {
+                "niblack" => AdaptiveMethod::Niblack {
+                    k: parse(params, 1, "k")?,
+                },
+                "sauvola" => AdaptiveMethod::Sauvola {
+                    k: parse(params, 1, "k")?,
+                    r: parse(params, 2, "r")?,
+                },
+                "wolf" => AdaptiveMethod::Wolf {
+                    k: parse(params, 1, "k")?,
+                },
+                "bernsen" => AdaptiveMethod::Bernsen {
+                    contrast_limit: parse(params, 1, "contrast-limit")?,
+                },
+                _ => AdaptiveMethod::Phansalkar {
+                    k: parse(params, 1, "k")?,
+                    r: parse(params, 2, "r")?,
+                    p: 2.0,
+                    q: 10.0,
+                },
+            };
+            if let AdaptiveMethod::Sauvola { r, .. } | AdaptiveMethod::Phansalkar { r, .. } = method
+            {
+                if r.is_nan() || r <= 0.0 {
+                    return Err("<r> must be greater than 0.".to_string());
+                }
+            }
+            Operation::Adaptive { window, method }
+        }
+        "erode" | "dilate" | "open" | "close" | "top-hat" | "black-hat" | "gradient"
+        | "hit-or-miss" | "skeleton" | "thin" | "fill-holes" => {
+            let operation = match operation {
+                "erode" => BinaryMorphology::Erode,
+                "dilate" => BinaryMorphology::Dilate,
+                "open" => BinaryMorphology::Open,
+                "close" => BinaryMorphology::Close,
+                "top-hat" => BinaryMorphology::TopHat,
+                "black-hat" => BinaryMorphology::BlackHat,
+                "gradient" => BinaryMorphology::Gradient,
+                "hit-or-miss" => BinaryMorphology::HitOrMiss,
+                "skeleton" => BinaryMorphology::Skeleton,
+                "thin" => BinaryMorphology::Thin,
+                _ => BinaryMorphology::FillHoles,
+            };
+            let (element, count) = if operation.uses_element() {
+                parse_element(params)?
+            } else {
+                (StructuringElement::ALL[0].clone(), 0)
+            };
+            if let Some(extra) = params.get(count) {
+                return Err(format!("Unexpected parameter: {}", extra));
+            }
+            Operation::BinaryMorphology { operation, element }
+        }
+        "gray-erode" | "gray-dilate" | "gray-open" | "gray-close" | "gray-top-hat"
+        | "gray-black-hat" | "gray-gradient" => {
+            let operation = match operation {
+                "gray-erode" => GrayMorphology::Erode,
+                "gray-dilate" => GrayMorphology::Dilate,
+                "gray-open" => GrayMorphology::Open,
+                "gray-close" => GrayMorphology::Close,
+                "gray-top-hat" => GrayMorphology::TopHat,
+                "gray-black-hat" => GrayMorphology::BlackHat,
+                _ => GrayMorphology::Gradient,
+            };
+            let (element, count) = parse_element(params)?;
+            Operation::GrayMorphology {
+                operation,
+                element,
+                intensity: parse_intensity(&params[count..])?,
+            }
+        }
+        _ => return Err(format!("Unknown operation: {}", operation)),
+    };
+
+    Ok(operation)
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    if args.len() < 3 {
+        return Err(USAGE.to_string());
+    }
+
+    let (input, output) = (&args[0], &args[1]);
+    let mut pipeline = Pipeline::new();
+    for step in args[2..].split(|arg| arg == "+") {
+        let (operation, params) = step.split_first().ok_or("Empty operation after +.")?;
+        pipeline.push(parse_operation(operation, params)?);
+    }
+
+    let format = ImageFormat::from_path(output)
+        .map_err(|_| format!("Can't infer output format from: {}", output))?;
+
+    let data = fs::read(input).map_err(|error| format!("Couldn't read {}: {}", input, error))?;
+    let image = Image::new_with_data(data).map_err(|error| error.to_string())?;
+    let result = pipeline.run(&image);
+
+    let bytes = result
+        .encode(ImageOutputFormat::from(format))
+        .map_err(|error| error.to_string())?;
+    fs::write(output, bytes).map_err(|error| format!("Couldn't write {}: {}", output, error))
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if let Err(error) = run(&args) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
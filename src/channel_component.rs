@@ -0,0 +1,151 @@
+use binhis::image::{Channel, Image};
+use wasm_bindgen::{Clamped, JsCast, JsValue};
+use web_sys::{CanvasRenderingContext2d, Event, HtmlCanvasElement, HtmlSelectElement, ImageData};
+use yew::{html, Component, NodeRef, Properties};
+
+use crate::shared_image::SharedImage;
+
+const HISTOGRAM_WIDTH: u32 = 512;
+const HISTOGRAM_HEIGHT: u32 = 160;
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub image: SharedImage,
+}
+
+pub enum Msg {
+    ChannelChanged(Event),
+}
+
+/// Shows one channel of any supported color space as a grayscale image
+/// along with its histogram.
+pub struct ChannelComponent {
+    channel: Channel,
+    channel_image: Image,
+    histogram: [u32; 256],
+    image_ref: NodeRef,
+    histogram_ref: NodeRef,
+}
+
+impl ChannelComponent {
+    fn update_channel(&mut self, image: &Image) {
+        self.channel_image = image.get_channel_image(self.channel);
+        self.histogram = image.get_channel_histogram(self.channel);
+    }
+
+    fn context(canvas_ref: &NodeRef) -> Option<CanvasRenderingContext2d> {
+        canvas_ref.cast::<HtmlCanvasElement>().map(|canvas| {
+            canvas
+                .get_context("2d")
+                .unwrap()
+                .unwrap()
+                .dyn_into::<CanvasRenderingContext2d>()
+                .unwrap()
+        })
+    }
+
+    fn draw_image(&self) {
+        let canvas_ctx = match Self::context(&self.image_ref) {
+            Some(canvas_ctx) => canvas_ctx,
+            None => return,
+        };
+        let image_data = ImageData::new_with_u8_clamped_array_and_sh(
+            Clamped(self.channel_image.get_data_ref()),
+            self.channel_image.get_width(),
+            self.channel_image.get_height(),
+        )
+        .unwrap();
+
+        canvas_ctx
+            .put_image_data(&image_data, 0.0, 0.0)
+            .expect("Couldn't draw image");
+    }
+
+    fn draw_histogram(&self) {
+        let canvas_ctx = match Self::context(&self.histogram_ref) {
+            Some(canvas_ctx) => canvas_ctx,
+            None => return,
+        };
+
+        let width = HISTOGRAM_WIDTH as f64;
+        let height = HISTOGRAM_HEIGHT as f64;
+        canvas_ctx.set_fill_style(&JsValue::from_str("#ffffff"));
+        canvas_ctx.fill_rect(0.0, 0.0, width, height);
+
+        let bin_width = width / 256.0;
+        let max = self.histogram.iter().copied().max().unwrap_or(0).max(1) as f64;
+        canvas_ctx.set_fill_style(&JsValue::from_str("#404040"));
+        for (i, count) in self.histogram.iter().enumerate() {
+            let bar = *count as f64 / max * height;
+            canvas_ctx.fill_rect(i as f64 * bin_width, height - bar, bin_width, bar);
+        }
+    }
+}
+
+impl Component for ChannelComponent {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &yew::Context<Self>) -> Self {
+        let image = &ctx.props().image;
+        let channel = Channel::Luminance;
+        Self {
+            channel,
+            channel_image: image.get_channel_image(channel),
+            histogram: image.get_channel_histogram(channel),
+            image_ref: NodeRef::default(),
+            histogram_ref: NodeRef::default(),
+        }
+    }
+
+    fn changed(&mut self, ctx: &yew::Context<Self>) -> bool {
+        self.update_channel(&ctx.props().image);
+
+        true
+    }
+
+    fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let link = ctx.link();
+
+        html! {
+            <details>
+                <summary>{"Channels"}</summary>
+                <select onchange={link.callback(|event: Event| Msg::ChannelChanged(event))}>
+                    { for Channel::ALL.iter().enumerate().map(|(i, channel)| html! {
+                        <option value={i.to_string()} selected={*channel == self.channel}>
+                            {channel.name()}
+                        </option>
+                    }) }
+                </select>
+                <div style="display: flex; align-items: flex-start; gap: 16px;">
+                    <canvas ref={self.image_ref.clone()}
+                        width={self.channel_image.get_width().to_string()}
+                        height={self.channel_image.get_height().to_string()}
+                    />
+                    <canvas ref={self.histogram_ref.clone()}
+                        width={HISTOGRAM_WIDTH.to_string()}
+                        height={HISTOGRAM_HEIGHT.to_string()}
+                    />
+                </div>
+            </details>
+        }
+    }
+
+    fn update(&mut self, ctx: &yew::Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::ChannelChanged(event) => {
+                let select: HtmlSelectElement = event.target().unwrap().dyn_into().unwrap();
+                let index = select.selected_index().max(0) as usize;
+                self.channel = Channel::ALL[index.min(Channel::ALL.len() - 1)];
+                self.update_channel(&ctx.props().image);
+
+                true
+            }
+        }
+    }
+
+    fn rendered(&mut self, _ctx: &yew::Context<Self>, _first_render: bool) {
+        self.draw_image();
+        self.draw_histogram();
+    }
+}
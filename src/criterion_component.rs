@@ -0,0 +1,145 @@
+use binhis::threshold::ThresholdMethod;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{CanvasRenderingContext2d, Event, HtmlCanvasElement, HtmlSelectElement};
+use yew::{html, Component, NodeRef, Properties};
+
+const CANVAS_WIDTH: u32 = 512;
+const CANVAS_HEIGHT: u32 = 200;
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub histogram: [u32; 256],
+    pub black_percent: f32,
+}
+
+pub enum Msg {
+    MethodChanged(Event),
+}
+
+/// Plots the criterion a selection method optimizes over the grayscale
+/// histogram it was computed from, with a marker at the picked level.
+pub struct CriterionComponent {
+    canvas_ref: NodeRef,
+    method: ThresholdMethod,
+}
+
+impl CriterionComponent {
+    fn draw(&self, ctx: &yew::Context<Self>) {
+        let canvas_ctx = match self.canvas_ref.cast::<HtmlCanvasElement>() {
+            Some(canvas) => canvas
+                .get_context("2d")
+                .unwrap()
+                .unwrap()
+                .dyn_into::<CanvasRenderingContext2d>()
+                .unwrap(),
+            None => return,
+        };
+
+        let histogram = &ctx.props().histogram;
+        let method = self.method.with_black_percent(ctx.props().black_percent);
+        let selection = method.compute(histogram);
+
+        let width = CANVAS_WIDTH as f64;
+        let height = CANVAS_HEIGHT as f64;
+        let bin_width = width / 256.0;
+        let x = |level: usize| level as f64 * bin_width + bin_width / 2.0;
+
+        canvas_ctx.set_fill_style(&JsValue::from_str("#ffffff"));
+        canvas_ctx.fill_rect(0.0, 0.0, width, height);
+
+        let max = histogram.iter().copied().max().unwrap_or(0).max(1) as f64;
+        canvas_ctx.set_fill_style(&JsValue::from_str("#d0d0d0"));
+        for (i, count) in histogram.iter().enumerate() {
+            let bar = *count as f64 / max * height;
+            canvas_ctx.fill_rect(i as f64 * bin_width, height - bar, bin_width, bar);
+        }
+
+        let finite = selection.criterion.iter().filter(|value| value.is_finite());
+        let low = finite.clone().copied().fold(f32::MAX, f32::min) as f64;
+        let high = finite.copied().fold(f32::MIN, f32::max) as f64;
+        if low <= high {
+            let range = (high - low).max(f64::EPSILON);
+            let y = |value: f32| height - (value as f64 - low) / range * (height - 10.0) - 5.0;
+
+            // Undefined levels break the curve instead of dropping to zero.
+            canvas_ctx.set_stroke_style(&JsValue::from_str("#0050c8"));
+            canvas_ctx.begin_path();
+            let mut drawing = false;
+            for (i, value) in selection.criterion.iter().enumerate() {
+                if !value.is_finite() {
+                    drawing = false;
+                } else if drawing {
+                    canvas_ctx.line_to(x(i), y(*value));
+                } else {
+                    canvas_ctx.move_to(x(i), y(*value));
+                    drawing = true;
+                }
+            }
+            canvas_ctx.stroke();
+        }
+
+        let threshold = selection.threshold as usize;
+        canvas_ctx.set_stroke_style(&JsValue::from_str("#e00000"));
+        canvas_ctx.begin_path();
+        canvas_ctx.move_to(x(threshold), 0.0);
+        canvas_ctx.line_to(x(threshold), height);
+        canvas_ctx.stroke();
+
+        canvas_ctx.set_fill_style(&JsValue::from_str("#e00000"));
+        canvas_ctx
+            .fill_text(&format!("threshold = {}", threshold), 4.0, 14.0)
+            .unwrap();
+    }
+}
+
+impl Component for CriterionComponent {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &yew::Context<Self>) -> Self {
+        Self {
+            canvas_ref: NodeRef::default(),
+            method: ThresholdMethod::Otsu,
+        }
+    }
+
+    fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let link = ctx.link();
+
+        html! {
+            <div>
+                <div>
+                    {"Selection criterion "}
+                    <select onchange={link.callback(|event: Event| Msg::MethodChanged(event))}>
+                        { for ThresholdMethod::ALL.iter().enumerate().map(|(i, method)| html! {
+                            <option value={i.to_string()}
+                                selected={method.name() == self.method.name()}>
+                                {method.name()}
+                            </option>
+                        }) }
+                    </select>
+                </div>
+                <canvas ref={self.canvas_ref.clone()}
+                    width={CANVAS_WIDTH.to_string()}
+                    height={CANVAS_HEIGHT.to_string()}
+                />
+            </div>
+        }
+    }
+
+    fn update(&mut self, _ctx: &yew::Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::MethodChanged(event) => {
+                let select: HtmlSelectElement = event.target().unwrap().dyn_into().unwrap();
+                let index = select.selected_index().max(0) as usize;
+                self.method = ThresholdMethod::ALL[index.min(ThresholdMethod::ALL.len() - 1)];
+
+                true
+            }
+        }
+    }
+
+    fn rendered(&mut self, ctx: &yew::Context<Self>, _first_render: bool) {
+        self.draw(ctx);
+    }
+}
@@ -0,0 +1,29 @@
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{Blob, BlobPropertyBag, HtmlAnchorElement, Url};
+
+/// Hands `bytes` to the browser as a file download named `filename`.
+pub fn download(bytes: &[u8], mime_type: &str, filename: &str) -> Result<(), JsValue> {
+    let parts = Array::of1(&Uint8Array::from(bytes));
+    let blob = Blob::new_with_u8_array_sequence_and_options(
+        &parts,
+        BlobPropertyBag::new().type_(mime_type),
+    )?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let window = web_sys::window().unwrap();
+    let document = window.document().unwrap();
+    let anchor: HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    anchor.set_href(&url);
+    anchor.set_download(filename);
+    anchor.click();
+
+    // Some browsers start the download after the click handler returns, so
+    // the URL is only revoked once the current task is done.
+    let revoke = Closure::once_into_js(move || {
+        let _ = Url::revoke_object_url(&url);
+    });
+    window.set_timeout_with_callback(revoke.unchecked_ref())?;
+
+    Ok(())
+}
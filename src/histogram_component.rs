@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use binhis::image::{ColorComponent, Image};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+use yew::{html, Component, NodeRef, Properties};
+
+const CANVAS_WIDTH: u32 = 512;
+const CANVAS_HEIGHT: u32 = 160;
+
+#[derive(Clone, PartialEq)]
+pub struct Histograms {
+    pub rgb: HashMap<ColorComponent, [u32; 256]>,
+    pub grayscale: [u32; 256],
+}
+
+impl Histograms {
+    pub fn of(image: &Image) -> Self {
+        Self {
+            rgb: image.get_histogram(),
+            grayscale: image.get_grayscale_histogram(),
+        }
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub original: Histograms,
+    pub processed: Histograms,
+}
+
+pub enum Setting {
+    Red,
+    Green,
+    Blue,
+    Grayscale,
+    LogScale,
+}
+
+pub enum Msg {
+    Toggle(Setting),
+}
+
+pub struct HistogramComponent {
+    original_ref: NodeRef,
+    processed_ref: NodeRef,
+    show_red: bool,
+    show_green: bool,
+    show_blue: bool,
+    show_grayscale: bool,
+    log_scale: bool,
+}
+
+impl HistogramComponent {
+    fn draw(&self, canvas_ref: &NodeRef, histograms: &Histograms) {
+        let canvas_ctx = match canvas_ref.cast::<HtmlCanvasElement>() {
+            Some(canvas) => canvas
+                .get_context("2d")
+                .unwrap()
+                .unwrap()
+                .dyn_into::<CanvasRenderingContext2d>()
+                .unwrap(),
+            None => return,
+        };
+
+        let width = CANVAS_WIDTH as f64;
+        let height = CANVAS_HEIGHT as f64;
+        canvas_ctx.set_fill_style(&JsValue::from_str("#ffffff"));
+        canvas_ctx.fill_rect(0.0, 0.0, width, height);
+
+        let mut channels = Vec::new();
+        if self.show_red {
+            channels.push((
+                &histograms.rgb[&ColorComponent::Red],
+                "rgba(255, 0, 0, 0.8)",
+            ));
+        }
+        if self.show_green {
+            channels.push((
+                &histograms.rgb[&ColorComponent::Green],
+                "rgba(0, 160, 0, 0.8)",
+            ));
+        }
+        if self.show_blue {
+            channels.push((
+                &histograms.rgb[&ColorComponent::Blue],
+                "rgba(0, 0, 255, 0.8)",
+            ));
+        }
+        if self.show_grayscale {
+            channels.push((&histograms.grayscale, "rgba(0, 0, 0, 0.8)"));
+        }
+
+        // All visible channels share one y-axis so their heights stay comparable.
+        let max = channels
+            .iter()
+            .flat_map(|(histogram, _)| histogram.iter())
+            .copied()
+            .max()
+            .unwrap_or(0);
+        if max == 0 {
+            return;
+        }
+
+        let scale = |count: u32| -> f64 {
+            if self.log_scale {
+                (count as f64).ln_1p() / (max as f64).ln_1p()
+            } else {
+                count as f64 / max as f64
+            }
+        };
+
+        let bin_width = width / 256.0;
+        for (histogram, color) in channels {
+            canvas_ctx.set_stroke_style(&JsValue::from_str(color));
+            canvas_ctx.begin_path();
+            canvas_ctx.move_to(0.0, height);
+            for (i, count) in histogram.iter().enumerate() {
+                let x = i as f64 * bin_width + bin_width / 2.0;
+                canvas_ctx.line_to(x, height - scale(*count) * height);
+            }
+            canvas_ctx.line_to(width, height);
+            canvas_ctx.stroke();
+        }
+    }
+}
+
+impl Component for HistogramComponent {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &yew::Context<Self>) -> Self {
+        Self {
+            original_ref: NodeRef::default(),
+            processed_ref: NodeRef::default(),
+            show_red: true,
+            show_green: true,
+            show_blue: true,
+            show_grayscale: true,
+            log_scale: false,
+        }
+    }
+
+    fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let link = ctx.link();
+
+        html! {
+            <div>
+                <div>
+                    <label>
+                        <input type="checkbox" checked={self.show_red}
+                            onchange={link.callback(|_| Msg::Toggle(Setting::Red))} />
+                        {"Red"}
+                    </label>
+                    <label>
+                        <input type="checkbox" checked={self.show_green}
+                            onchange={link.callback(|_| Msg::Toggle(Setting::Green))} />
+                        {"Green"}
+                    </label>
+                    <label>
+                        <input type="checkbox" checked={self.show_blue}
+                            onchange={link.callback(|_| Msg::Toggle(Setting::Blue))} />
+                        {"Blue"}
+                    </label>
+                    <label>
+                        <input type="checkbox" checked={self.show_grayscale}
+                            onchange={link.callback(|_| Msg::Toggle(Setting::Grayscale))} />
+                        {"Grayscale"}
+                    </label>
+                    <label>
+                        <input type="checkbox" checked={self.log_scale}
+                            onchange={link.callback(|_| Msg::Toggle(Setting::LogScale))} />
+                        {"Log scale"}
+                    </label>
+                </div>
+                <div>
+                    <div>{"Original"}</div>
+                    <canvas ref={self.original_ref.clone()}
+                        width={CANVAS_WIDTH.to_string()}
+                        height={CANVAS_HEIGHT.to_string()}
+                    />
+                </div>
+                <div>
+                    <div>{"Processed"}</div>
+                    <canvas ref={self.processed_ref.clone()}
+                        width={CANVAS_WIDTH.to_string()}
+                        height={CANVAS_HEIGHT.to_string()}
+                    />
+                </div>
+            </div>
+        }
+    }
+
+    fn update(&mut self, _ctx: &yew::Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Toggle(Setting::Red) => self.show_red = !self.show_red,
+            Msg::Toggle(Setting::Green) => self.show_green = !self.show_green,
+            Msg::Toggle(Setting::Blue) => self.show_blue = !self.show_blue,
+            Msg::Toggle(Setting::Grayscale) => self.show_grayscale = !self.show_grayscale,
+            Msg::Toggle(Setting::LogScale) => self.log_scale = !self.log_scale,
+        }
+
+        true
+    }
+
+    fn rendered(&mut self, ctx: &yew::Context<Self>, _first_render: bool) {
+        self.draw(&self.original_ref, &ctx.props().original);
+        self.draw(&self.processed_ref, &ctx.props().processed);
+    }
+}
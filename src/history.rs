@@ -0,0 +1,192 @@
+use crate::{image::Image, pipeline::Pipeline};
+
+const MAX_ENTRIES: usize = 100;
+
+struct Entry {
+    pipeline: Pipeline,
+    snapshot: Option<Image>,
+}
+
+/// Undo/redo stacks of pipeline states. Pipelines are cheap to keep, so every
+/// state stores one, while rendered snapshots are only cached for the most
+/// recent states that fit in the byte budget. States without a snapshot have
+/// to be recomputed by running their pipeline again.
+pub struct History {
+    undo: Vec<Entry>,
+    redo: Vec<Entry>,
+    snapshot_budget: usize,
+}
+
+impl History {
+    pub fn new(snapshot_budget: usize) -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            snapshot_budget,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Saves the state that is about to be replaced. Recording a new state
+    /// discards everything that could have been redone.
+    pub fn record(&mut self, pipeline: Pipeline, output: Image) {
+        self.redo.clear();
+        self.undo.push(Entry {
+            pipeline,
+            snapshot: Some(output),
+        });
+        if self.undo.len() > MAX_ENTRIES {
+            self.undo.remove(0);
+        }
+
+        self.evict_snapshots();
+    }
+
+    /// Swaps the current state for the previous one. The returned snapshot is
+    /// `None` if it has been evicted.
+    pub fn undo(&mut self, pipeline: Pipeline, output: Image) -> Option<(Pipeline, Option<Image>)> {
+        let entry = self.undo.pop()?;
+        self.redo.push(Entry {
+            pipeline,
+            snapshot: Some(output),
+        });
+        self.evict_snapshots();
+
+        Some((entry.pipeline, entry.snapshot))
+    }
+
+    pub fn redo(&mut self, pipeline: Pipeline, output: Image) -> Option<(Pipeline, Option<Image>)> {
+        let entry = self.redo.pop()?;
+        self.undo.push(Entry {
+            pipeline,
+            snapshot: Some(output),
+        });
+        self.evict_snapshots();
+
+        Some((entry.pipeline, entry.snapshot))
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Drops snapshots, farthest from the current state first, until the
+    /// cached images fit in the budget.
+    fn evict_snapshots(&mut self) {
+        let mut used = 0;
+        let mut undo = self.undo.iter_mut().rev();
+        let mut redo = self.redo.iter_mut().rev();
+
+        loop {
+            let entries = [undo.next(), redo.next()];
+            if entries.iter().all(Option::is_none) {
+                break;
+            }
+
+            for entry in entries.into_iter().flatten() {
+                if let Some(snapshot) = &entry.snapshot {
+                    let size = snapshot.get_data_ref().len();
+                    if used + size > self.snapshot_budget {
+                        entry.snapshot = None;
+                    } else {
+                        used += size;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{DynamicImage, ImageOutputFormat, Luma};
+
+    use super::*;
+    use crate::pipeline::Operation;
+
+    /// Bytes of a decoded 4x4 image, RGBA.
+    const IMAGE_SIZE: usize = 4 * 4 * 4;
+
+    fn image(level: u8) -> Image {
+        let pixels = image::GrayImage::from_pixel(4, 4, Luma([level]));
+        let mut png = Cursor::new(Vec::new());
+        DynamicImage::ImageLuma8(pixels)
+            .write_to(&mut png, ImageOutputFormat::Png)
+            .unwrap();
+
+        Image::new_with_data(png.into_inner()).unwrap()
+    }
+
+    fn pipeline(step: usize) -> Pipeline {
+        let mut pipeline = Pipeline::new();
+        pipeline.push(Operation::MultiOtsu(step));
+
+        pipeline
+    }
+
+    #[test]
+    fn oldest_snapshots_are_evicted_first() {
+        let mut history = History::new(3 * IMAGE_SIZE);
+        for i in 0..5 {
+            history.record(pipeline(i), image(i as u8));
+        }
+
+        let cached: Vec<bool> = history
+            .undo
+            .iter()
+            .map(|entry| entry.snapshot.is_some())
+            .collect();
+        assert_eq!(cached, [false, false, true, true, true]);
+        // Evicted states keep their pipeline.
+        assert_eq!(history.undo[0].pipeline, pipeline(0));
+    }
+
+    #[test]
+    fn entries_are_capped() {
+        let mut history = History::new(0);
+        for i in 0..MAX_ENTRIES + 5 {
+            history.record(pipeline(i), image(0));
+        }
+
+        assert_eq!(history.undo.len(), MAX_ENTRIES);
+        assert_eq!(history.undo[0].pipeline, pipeline(5));
+    }
+
+    #[test]
+    fn recording_clears_redo() {
+        let mut history = History::new(10 * IMAGE_SIZE);
+        history.record(pipeline(0), image(0));
+        history.undo(pipeline(1), image(1)).unwrap();
+        assert!(history.can_redo());
+
+        history.record(pipeline(0), image(0));
+        assert!(!history.can_redo());
+        assert!(history.redo(pipeline(2), image(2)).is_none());
+    }
+
+    #[test]
+    fn undo_then_redo_swaps_the_states_back() {
+        let mut history = History::new(10 * IMAGE_SIZE);
+        history.record(pipeline(0), image(0));
+
+        let (previous, snapshot) = history.undo(pipeline(1), image(1)).unwrap();
+        assert_eq!(previous, pipeline(0));
+        assert!(snapshot == Some(image(0)));
+        assert!(!history.can_undo());
+
+        let (next, snapshot) = history.redo(previous, snapshot.unwrap()).unwrap();
+        assert_eq!(next, pipeline(1));
+        assert!(snapshot == Some(image(1)));
+        assert!(history.can_undo() && !history.can_redo());
+    }
+}
@@ -1,6 +1,113 @@
-use std::{collections::HashMap, io::Cursor};
+mod adaptive;
+mod adjustment;
+mod clahe;
+pub mod color;
+mod gray_morphology;
+mod intensity;
+mod lut;
+mod matching;
+mod morphology;
+mod threshold_mode;
+mod tone;
+
+use std::{collections::HashMap, fmt, io::Cursor};
+
+use image::{
+    error::UnsupportedErrorKind, imageops, io::Reader, DynamicImage, ImageOutputFormat, RgbaImage,
+};
+
+use crate::{
+    metrics::{BinarizationMetrics, MetricsError},
+    threshold::{compute_multi_otsu_thresholds, ThresholdMethod, ThresholdSelection},
+};
+
+pub use adaptive::AdaptiveMethod;
+pub use adjustment::Adjustment;
+pub use color::Channel;
+pub use gray_morphology::GrayMorphology;
+pub use intensity::Intensity;
+pub use lut::{Lut, RgbHistograms};
+pub use matching::HistogramTarget;
+pub use morphology::{BinaryMorphology, StructuringElement};
+pub use threshold_mode::ThresholdMode;
+pub use tone::{Curve, Levels};
+
+pub(crate) use morphology::skeletonize;
+
+/// Largest width or height accepted by the decoder, the limit of canvases in
+/// most browsers.
+pub const MAX_DIMENSION: u32 = 16384;
+/// Largest pixel count accepted by the decoder, a 64 MiB RGBA buffer. The
+/// component keeps several copies of it next to the history snapshots, so
+/// this is what keeps uploads within what a browser tab can hold.
+pub const MAX_PIXELS: u64 = 4096 * 4096;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    UnknownFormat,
+    TruncatedData,
+    UnsupportedColorType(String),
+    DimensionsTooLarge { width: u32, height: u32 },
+    Corrupted(String),
+    EncodingFailed(String),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::UnknownFormat => write!(f, "Unknown or unsupported image format."),
+            ImageError::TruncatedData => write!(f, "Image data is truncated."),
+            ImageError::UnsupportedColorType(color) => {
+                write!(f, "Unsupported color type: {}.", color)
+            }
+            ImageError::DimensionsTooLarge { width, height } => write!(
+                f,
+                "Image dimensions {}x{} exceed the maximum of {} pixels per side or {} \
+                 megapixels in total.",
+                width,
+                height,
+                MAX_DIMENSION,
+                MAX_PIXELS / (1024 * 1024)
+            ),
+            ImageError::Corrupted(reason) => write!(f, "Unable to decode image: {}", reason),
+            ImageError::EncodingFailed(reason) => write!(f, "Unable to encode image: {}", reason),
+        }
+    }
+}
 
-use image::{io::Reader, DynamicImage};
+impl std::error::Error for ImageError {}
+
+impl From<image::ImageError> for ImageError {
+    fn from(error: image::ImageError) -> Self {
+        match error {
+            image::ImageError::Unsupported(error) => match error.kind() {
+                UnsupportedErrorKind::Color(color) => {
+                    ImageError::UnsupportedColorType(format!("{:?}", color))
+                }
+                UnsupportedErrorKind::Format(_) => ImageError::UnknownFormat,
+                UnsupportedErrorKind::GenericFeature(feature) => ImageError::Corrupted(feature),
+                _ => ImageError::Corrupted(error.to_string()),
+            },
+            image::ImageError::IoError(error)
+                if error.kind() == std::io::ErrorKind::UnexpectedEof =>
+            {
+                ImageError::TruncatedData
+            }
+            image::ImageError::Decoding(error) if is_end_of_data(&error) => {
+                ImageError::TruncatedData
+            }
+            error => ImageError::Corrupted(error.to_string()),
+        }
+    }
+}
+
+/// PNG and GIF report running out of data as a format error whose kind isn't
+/// public, only the message tells it apart.
+fn is_end_of_data(error: &image::error::DecodingError) -> bool {
+    let message = error.to_string().to_lowercase();
+
+    message.contains("unexpected end of data") || message.contains("unexpected eof")
+}
 
 #[allow(dead_code)]
 #[repr(usize)]
@@ -12,7 +119,7 @@ pub enum ColorComponent {
     Alpha = 3,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct Image {
     width: u32,
     height: u32,
@@ -20,14 +127,14 @@ pub struct Image {
 }
 
 impl Image {
-    pub fn new_with_data(data: Vec<u8>) -> Self {
-        let image = Self::decode_data(data);
+    pub fn new_with_data(data: Vec<u8>) -> Result<Self, ImageError> {
+        let image = Self::decode_data(data)?;
 
-        Self {
+        Ok(Self {
             data: image.to_rgba8().into_vec(),
             width: image.width(),
             height: image.height(),
-        }
+        })
     }
 
     pub fn get_width(&self) -> u32 {
@@ -42,168 +149,99 @@ impl Image {
         &self.data
     }
 
-    pub fn get_histogram(&self) -> HashMap<ColorComponent, [u32; 256]> {
-        let mut histogram = HashMap::new();
-
-        for component in &[
-            ColorComponent::Red,
-            ColorComponent::Green,
-            ColorComponent::Blue,
-        ] {
-            histogram.insert(*component, [0; 256]);
+    /// Downscaled copy fitting in a `max_size` square, keeping the aspect
+    /// ratio. Images already small enough are returned as they are.
+    pub fn thumbnail(&self, max_size: u32) -> Self {
+        if self.width <= max_size && self.height <= max_size {
+            return self.clone();
         }
 
-        for pixel in self.data.chunks(4) {
-            histogram.get_mut(&ColorComponent::Red).unwrap()[pixel[0] as usize] += 1;
-            histogram.get_mut(&ColorComponent::Green).unwrap()[pixel[1] as usize] += 1;
-            histogram.get_mut(&ColorComponent::Blue).unwrap()[pixel[2] as usize] += 1;
-        }
+        let scale = max_size as f64 / self.width.max(self.height) as f64;
+        let width = ((self.width as f64 * scale).round() as u32).max(1);
+        let height = ((self.height as f64 * scale).round() as u32).max(1);
+        let image = RgbaImage::from_raw(self.width, self.height, self.data.clone())
+            .expect("Image buffer doesn't match its dimensions.");
 
-        histogram
+        Self {
+            data: imageops::thumbnail(&image, width, height).into_vec(),
+            width,
+            height,
+        }
     }
 
-    pub fn get_grayscale_histogram(&self) -> [u32; 256] {
-        let mut histogram = [0; 256];
+    pub fn encode(&self, format: ImageOutputFormat) -> Result<Vec<u8>, ImageError> {
+        let image = RgbaImage::from_raw(self.width, self.height, self.data.clone())
+            .expect("Image buffer doesn't match its dimensions.");
+        let mut bytes = Cursor::new(Vec::new());
+        DynamicImage::ImageRgba8(image)
+            .write_to(&mut bytes, format)
+            .map_err(|error| ImageError::EncodingFailed(error.to_string()))?;
 
-        for pixel in self.data.chunks(4) {
-            let grayscale = (pixel[0] as f32 * 0.2126
-                + pixel[1] as f32 * 0.7152
-                + pixel[2] as f32 * 0.0722) as u8;
-
-            histogram[grayscale as usize] += 1;
-        }
-
-        histogram
+        Ok(bytes.into_inner())
     }
 
-    pub fn get_equalized_image(&self) -> Self {
-        let histogram = self.get_histogram();
-        let mut cdf = HashMap::new();
-        let mut min = HashMap::new();
-
-        for component in &[
-            ColorComponent::Red,
-            ColorComponent::Green,
-            ColorComponent::Blue,
-        ] {
-            cdf.insert(*component, [0; 256]);
-            min.insert(*component, u32::MAX);
-        }
+    /// True if every pixel is pure black or pure white in all color channels.
+    pub fn is_binary(&self) -> bool {
+        self.data
+            .chunks(4)
+            .all(|pixel| pixel[..3].iter().all(|&value| value == 0 || value == 255))
+    }
 
-        for component in &[
-            ColorComponent::Red,
-            ColorComponent::Green,
-            ColorComponent::Blue,
-        ] {
-            let mut sum = 0;
-
-            for i in 0..256 {
-                sum += histogram.get(component).unwrap()[i];
-                cdf.get_mut(component).unwrap()[i] = sum;
-                if sum < min[component] {
-                    min.insert(*component, sum);
-                }
+    /// Encodes as a 1-bit grayscale PNG. Pixels with luminance of at least 128
+    /// are stored as white, so binarized output round-trips exactly.
+    pub fn encode_binary_png(&self) -> Result<Vec<u8>, ImageError> {
+        let row_bytes = (self.width as usize).div_ceil(8);
+        let mut packed = vec![0u8; row_bytes * self.height as usize];
+        for (i, pixel) in self.data.chunks(4).enumerate() {
+            let (x, y) = (i % self.width as usize, i / self.width as usize);
+            if Self::luminance(pixel) >= 128 {
+                packed[y * row_bytes + x / 8] |= 0x80 >> (x % 8);
             }
         }
 
-        let pixels = self.width * self.height;
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::One);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&packed))
+            .map_err(|error| ImageError::EncodingFailed(error.to_string()))?;
 
-        let mut data = self.data.clone();
-        for (i, chunk) in self.data.chunks(4).enumerate() {
-            for component in &[
-                ColorComponent::Red,
-                ColorComponent::Green,
-                ColorComponent::Blue,
-            ] {
-                let up =
-                    (cdf[component][chunk[*component as usize] as usize] - min[component]) as f32;
-                let down = (pixels - min[component]) as f32;
-                let val = (up / down * 255.0).round() as u8;
-                data[(i * 4) + *component as usize] = val;
-            }
-        }
+        Ok(bytes)
+    }
 
-        Self {
-            data,
-            width: self.width,
-            height: self.height,
-        }
+    pub fn get_histogram(&self) -> HashMap<ColorComponent, [u32; 256]> {
+        [
+            (ColorComponent::Red, Channel::Red),
+            (ColorComponent::Green, Channel::Green),
+            (ColorComponent::Blue, Channel::Blue),
+        ]
+        .into_iter()
+        .map(|(component, channel)| (component, self.get_channel_histogram(channel)))
+        .collect()
     }
 
-    pub fn get_stretched_image(&self) -> Self {
-        let histogram = self.get_histogram();
-        let mut min = HashMap::new();
-        let mut max = HashMap::new();
-
-        for component in &[
-            ColorComponent::Red,
-            ColorComponent::Green,
-            ColorComponent::Blue,
-        ] {
-            min.insert(*component, 255u8);
-            max.insert(*component, 0u8);
-        }
+    pub fn get_grayscale_histogram(&self) -> [u32; 256] {
+        self.get_channel_histogram(Channel::Luminance)
+    }
 
-        for component in &[
-            ColorComponent::Red,
-            ColorComponent::Green,
-            ColorComponent::Blue,
-        ] {
-            for i in 0..256 {
-                if histogram.get(component).unwrap()[i] > 0 {
-                    if (i as u8) < *min.get(component).unwrap() {
-                        min.insert(*component, i as u8);
-                    }
-
-                    if (i as u8) > *max.get(component).unwrap() {
-                        max.insert(*component, i as u8);
-                    }
-                }
-            }
-        }
+    pub fn get_channel_histogram(&self, channel: Channel) -> [u32; 256] {
+        let mut histogram = [0; 256];
 
-        let mut data = self.data.clone();
-        for (i, chunk) in self.data.chunks(4).enumerate() {
-            for component in &[
-                ColorComponent::Red,
-                ColorComponent::Green,
-                ColorComponent::Blue,
-            ] {
-                let min = min[component];
-                let max = max[component];
-                data[(i * 4) + *component as usize] =
-                    ((chunk[*component as usize] - min) as f32 / (max - min) as f32 * 255.0) as u8;
-            }
+        for pixel in self.data.chunks(4) {
+            histogram[channel.of(pixel) as usize] += 1;
         }
 
-        Self {
-            data,
-            width: self.width,
-            height: self.height,
-        }
+        histogram
     }
 
-    pub fn threshold(&self, (low, high): (u8, u8)) -> Self {
+    /// Grayscale image of a single channel, alpha is kept.
+    pub fn get_channel_image(&self, channel: Channel) -> Self {
         let mut data = self.data.clone();
-        for (i, chunk) in self.data.chunks(4).enumerate() {
-            let mut val = 0;
-            for component in &[
-                ColorComponent::Red,
-                ColorComponent::Green,
-                ColorComponent::Blue,
-            ] {
-                if chunk[*component as usize] >= low && chunk[*component as usize] <= high {
-                    val = 255;
-                }
-            }
-
-            for component in &[
-                ColorComponent::Red,
-                ColorComponent::Green,
-                ColorComponent::Blue,
-            ] {
-                data[(i * 4) + *component as usize] = val;
-            }
+        for (i, pixel) in self.data.chunks(4).enumerate() {
+            let value = channel.of(pixel);
+            data[i * 4..i * 4 + 3].fill(value);
         }
 
         Self {
@@ -213,257 +251,115 @@ impl Image {
         }
     }
 
-    pub fn percent_black_selection(&self, percent: f32) -> Self {
-        let histogram = self.get_grayscale_histogram();
-
-        let pixels = ((self.width * self.height) as f32 * percent).floor() as u32;
-        let mut sum = 0;
-        let mut threshold = 0;
-        for i in 0..256 {
-            sum += histogram[i];
-            if sum >= pixels {
-                threshold = i;
-                break;
-            }
-        }
-
-        self.threshold((threshold as u8, 255))
+    pub fn get_equalized_image(&self) -> Self {
+        self.apply_lut(&Lut::equalization(&self.get_rgb_histograms()))
     }
 
-    pub fn mean_iterative_selection(&self) -> Self {
-        let histogram = self.get_grayscale_histogram();
-        let mut mean = 0.0;
-        let mut prev_mean = 0.0;
-        let mut count = 0;
-
-        for i in 0..256 {
-            mean += i as f32 * histogram[i] as f32;
-            count += histogram[i];
-        }
-
-        mean /= count as f32;
-
-        while (mean - prev_mean).abs() > 0.01 {
-            let mut low_mean = 0.0;
-            let mut low_count = 0;
-            let mut high_mean = 0.0;
-            let mut high_count = 0;
-
-            for i in 0..256 {
-                if (i as f32) < mean {
-                    low_mean += i as f32 * histogram[i] as f32;
-                    low_count += histogram[i];
-                } else {
-                    high_mean += i as f32 * histogram[i] as f32;
-                    high_count += histogram[i];
-                }
-            }
+    pub fn get_stretched_image(&self) -> Self {
+        self.apply_lut(&Lut::stretch(&self.get_rgb_histograms()))
+    }
 
-            low_mean /= low_count as f32;
-            high_mean /= high_count as f32;
+    /// Sets a pixel white if any of red, green or blue is in `low..=high`,
+    /// see `threshold_with` for other modes.
+    pub fn threshold(&self, (low, high): (u8, u8)) -> Self {
+        self.threshold_with(ThresholdMode::Any { low, high })
+    }
 
-            prev_mean = mean;
-            mean = (low_mean + high_mean) / 2.0;
-        }
+    pub fn percent_black_selection(&self, percent: f32) -> Self {
+        self.auto_threshold(ThresholdMethod::PercentBlack(percent))
+    }
 
-        self.threshold((mean as u8, 255))
+    pub fn mean_iterative_selection(&self) -> Self {
+        self.auto_threshold(ThresholdMethod::MeanIterative)
     }
 
     pub fn entropy_selection(&self) -> Self {
-        let histogram = self
-            .get_grayscale_histogram()
-            .map(|x| x as f32 / (self.width * self.height) as f32);
-        let mut max_sum = std::f32::MIN;
-        let mut f;
-        let mut pt = 0.0;
-
-        let mut max_low = histogram[0];
-        let mut max_high;
-        let mut ht = 0.0;
-        let mut ht_total = 0.0;
-
-        for i in 0..256 {
-            if histogram[i] > 0.0 {
-                ht_total -= histogram[i] * (histogram[i] as f32).log2();
-            }
-        }
-
-        let mut threshold = 0;
-        for i in 0..256 {
-            pt += histogram[i];
-            max_low = max_low.max(histogram[i]);
-            max_high = if i < 255 {
-                histogram[i + 1]
-            } else {
-                histogram[i]
-            };
-
-            for j in i + 2..256 {
-                if histogram[j] > max_high {
-                    max_high = histogram[j];
-                }
-            }
-
-            if histogram[i] > 0.0 {
-                ht -= histogram[i] * (histogram[i] as f32).log2();
-            }
-
-            f = ht * (pt as f32).log2() / (ht_total * (max_low as f32).log2())
-                + (1.0 - ht / ht_total) * (1.0 - pt as f32).log2() / (max_high as f32).log2();
-
-            if f > max_sum as f32 {
-                max_sum = f;
-                threshold = i;
-            }
-        }
-
-        self.threshold((threshold as u8, 255))
+        self.auto_threshold(ThresholdMethod::Entropy)
     }
 
     pub fn minimum_error_selection(&self) -> Self {
-        let histogram = self
-            .get_grayscale_histogram()
-            .map(|x| x as f32 / (self.width * self.height) as f32);
-
-        let mut min_value = std::f32::MAX;
-        let mut j;
-        let mut p1 = 0.0;
-        let mut p2 = 0.0;
-        let mut s1;
-        let mut s2;
-        let mut fv;
-        let mut u1;
-        let mut u2;
-        let mut pi1 = 0.0;
-        let mut pi2 = 0.0;
-
-        for i in 0..256 {
-            p2 += histogram[i];
-            pi2 += i as f32 * histogram[i];
-        }
-
-        let mut threshold = 0;
-        for i in 0..256 {
-            p1 += histogram[i];
-            p2 -= histogram[i];
-            pi1 += i as f32 * histogram[i];
-            pi2 -= i as f32 * histogram[i];
-
-            u1 = if p1 > 0.0 {
-                pi1 as f32 / p1 as f32
-            } else {
-                0.0
-            };
-            u2 = if p2 > 0.0 {
-                pi2 as f32 / p2 as f32
-            } else {
-                0.0
-            };
-
-            s1 = 0.0;
-            if p1 > 0.0 {
-                for j in 0..=i {
-                    fv = j as f32 - u1;
-                    s1 += fv * fv * histogram[j] as f32;
-                }
-                s1 /= p1 as f32;
-            }
-
-            s2 = 0.0;
-            if p2 > 0.0 {
-                for j in i + 1..256 {
-                    fv = j as f32 - u2;
-                    s2 += fv * fv * histogram[j] as f32;
-                }
-                s2 /= p2 as f32;
-            }
-
-            j = 1.0 + 2.0 * ((p1 * s1.log2() - p1.log2()) + p2 * (s2.log2() - p2.log2()));
-            if j == std::f32::NAN || j == -std::f32::INFINITY {
-                continue;
-            }
-
-            if j < min_value {
-                min_value = j;
-                threshold = i;
-            }
-        }
-
-        self.threshold((threshold as u8, 255))
+        self.auto_threshold(ThresholdMethod::MinimumError)
     }
 
     pub fn fuzzy_minimum_error_selection(&self) -> Self {
-        let histogram = self.get_grayscale_histogram();
-        let mut min_error = std::f32::MAX;
-        let mut threshold = 0;
-
-        let mut max = 0;
-        let mut min = 255;
+        self.auto_threshold(ThresholdMethod::FuzzyMinimumError)
+    }
 
-        for i in 0..256 {
-            if histogram[i] > 0 {
-                if i > max {
-                    max = i;
-                }
-                if i < min {
-                    min = i;
-                }
-            }
-        }
+    pub fn otsu_selection(&self) -> Self {
+        self.auto_threshold(ThresholdMethod::Otsu)
+    }
 
-        let c = max - min;
+    pub fn select_threshold(&self, method: ThresholdMethod) -> ThresholdSelection {
+        method.compute(&self.get_grayscale_histogram())
+    }
 
-        for t in 0..255 {
-            let mut mu0 = 0.0;
-            let mut c0 = 0;
-            for i in 0..=t {
-                mu0 += i as f32 * histogram[i] as f32;
-                c0 += histogram[i];
-            }
-            mu0 /= c0 as f32;
+    pub fn auto_threshold(&self, method: ThresholdMethod) -> Self {
+        self.threshold((self.select_threshold(method).threshold, 255))
+    }
 
-            let mut mu1 = 0.0;
-            let mut c1 = 0;
-            for i in t + 1..256 {
-                mu1 += i as f32 * histogram[i] as f32;
-                c1 += histogram[i];
-            }
-            mu1 /= c1 as f32;
+    /// Scores the result of `auto_threshold` against a ground-truth mask.
+    pub fn evaluate_threshold(
+        &self,
+        method: ThresholdMethod,
+        ground_truth: &Image,
+    ) -> Result<BinarizationMetrics, MetricsError> {
+        BinarizationMetrics::compute(&self.auto_threshold(method), ground_truth)
+    }
 
-            let mut e = 0.0;
-            for i in 0..=t {
-                e += Self::shannon(c as f32 / (c as f32 + (i as f32 - mu0).abs()))
-                    * histogram[i] as f32;
-            }
+    pub fn multi_otsu_thresholds(&self, count: usize) -> Vec<u8> {
+        compute_multi_otsu_thresholds(&self.get_grayscale_histogram(), count)
+    }
 
-            for i in t + 1..256 {
-                e += Self::shannon(c as f32 / (c as f32 + (i as f32 - mu1).abs()))
-                    * histogram[i] as f32;
-            }
+    pub fn multi_otsu_selection(&self, count: usize) -> Self {
+        let thresholds = self.multi_otsu_thresholds(count);
+        let step = 255.0 / thresholds.len() as f32;
 
-            e /= (self.width * self.height) as f32;
+        let mut data = self.data.clone();
+        for (i, chunk) in self.data.chunks(4).enumerate() {
+            let gray = Self::luminance(chunk);
+            let class = thresholds.iter().filter(|t| gray >= **t).count();
+            let val = (class as f32 * step).round() as u8;
 
-            if e < min_error {
-                min_error = e;
-                threshold = t;
+            for component in &[
+                ColorComponent::Red,
+                ColorComponent::Green,
+                ColorComponent::Blue,
+            ] {
+                data[(i * 4) + *component as usize] = val;
             }
         }
 
-        self.threshold((threshold as u8, 255))
+        Self {
+            data,
+            width: self.width,
+            height: self.height,
+        }
     }
-    fn shannon(x: f32) -> f32 {
-        if x == 0.0 {
-            0.0
-        } else {
-            -x * x.log2() - (1.0 - x) * (1.0 - x).log2()
+
+    pub(crate) fn luminance(pixel: &[u8]) -> u8 {
+        (pixel[0] as f32 * 0.2126 + pixel[1] as f32 * 0.7152 + pixel[2] as f32 * 0.0722) as u8
+    }
+
+    fn decode_data(data: Vec<u8>) -> Result<DynamicImage, ImageError> {
+        let (width, height) = Self::reader(&data)?.into_dimensions()?;
+        if width > MAX_DIMENSION
+            || height > MAX_DIMENSION
+            || width as u64 * height as u64 > MAX_PIXELS
+        {
+            return Err(ImageError::DimensionsTooLarge { width, height });
         }
+
+        Ok(Self::reader(&data)?.decode()?)
     }
 
-    fn decode_data(data: Vec<u8>) -> DynamicImage {
-        let reader = Reader::new(Cursor::new(&data[..]))
+    fn reader(data: &[u8]) -> Result<Reader<Cursor<&[u8]>>, ImageError> {
+        let reader = Reader::new(Cursor::new(data))
             .with_guessed_format()
-            .expect("Couldn't guess file format.");
+            .map_err(|_| ImageError::UnknownFormat)?;
 
-        reader.decode().expect("Unable to decode image.")
+        match reader.format() {
+            Some(_) => Ok(reader),
+            None => Err(ImageError::UnknownFormat),
+        }
     }
 }
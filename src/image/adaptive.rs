@@ -0,0 +1,345 @@
+use super::{ColorComponent, Image};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AdaptiveMethod {
+    /// T = m + k * s
+    Niblack { k: f32 },
+    /// T = m * (1 + k * (s / r - 1))
+    Sauvola { k: f32, r: f32 },
+    /// T = (1 - k) * m + k * M + k * s / R * (m - M), where M is the darkest
+    /// gray level of the image and R the largest local deviation.
+    Wolf { k: f32 },
+    /// T = (min + max) / 2, windows with contrast below the limit are
+    /// treated as uniform background or foreground.
+    Bernsen { contrast_limit: u8 },
+    /// Sauvola extended for low contrast images, on intensities in 0.0..=1.0:
+    /// T = m * (1 + p * exp(-q * m) + k * (s / r - 1))
+    Phansalkar { k: f32, r: f32, p: f32, q: f32 },
+}
+
+impl AdaptiveMethod {
+    pub const ALL: [AdaptiveMethod; 5] = [
+        AdaptiveMethod::Niblack { k: -0.2 },
+        AdaptiveMethod::Sauvola { k: 0.5, r: 128.0 },
+        AdaptiveMethod::Wolf { k: 0.5 },
+        AdaptiveMethod::Bernsen { contrast_limit: 15 },
+        AdaptiveMethod::Phansalkar {
+            k: 0.25,
+            r: 0.5,
+            p: 2.0,
+            q: 10.0,
+        },
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AdaptiveMethod::Niblack { .. } => "Niblack",
+            AdaptiveMethod::Sauvola { .. } => "Sauvola",
+            AdaptiveMethod::Wolf { .. } => "Wolf-Jolion",
+            AdaptiveMethod::Bernsen { .. } => "Bernsen",
+            AdaptiveMethod::Phansalkar { .. } => "Phansalkar",
+        }
+    }
+}
+
+/// Summed-area tables of gray levels and their squares, so the mean and
+/// deviation of any window cost four lookups each.
+struct IntegralImage {
+    width: usize,
+    sum: Vec<u64>,
+    squared_sum: Vec<u64>,
+}
+
+impl IntegralImage {
+    fn new(gray: &[u8], width: usize, height: usize) -> Self {
+        let stride = width + 1;
+        let mut sum = vec![0u64; stride * (height + 1)];
+        let mut squared_sum = vec![0u64; stride * (height + 1)];
+
+        for y in 0..height {
+            let mut row_sum = 0u64;
+            let mut row_squared_sum = 0u64;
+            for x in 0..width {
+                let value = gray[y * width + x] as u64;
+                row_sum += value;
+                row_squared_sum += value * value;
+
+                let i = (y + 1) * stride + x + 1;
+                sum[i] = sum[i - stride] + row_sum;
+                squared_sum[i] = squared_sum[i - stride] + row_squared_sum;
+            }
+        }
+
+        Self {
+            width,
+            sum,
+            squared_sum,
+        }
+    }
+
+    /// Mean and standard deviation of the window spanning `x0..x1` and `y0..y1`.
+    fn stats(&self, (x0, y0): (usize, usize), (x1, y1): (usize, usize)) -> (f32, f32) {
+        let stride = self.width + 1;
+        let area = |table: &[u64]| {
+            table[y1 * stride + x1] + table[y0 * stride + x0]
+                - table[y0 * stride + x1]
+                - table[y1 * stride + x0]
+        };
+
+        let count = ((x1 - x0) * (y1 - y0)) as f64;
+        let mean = area(&self.sum) as f64 / count;
+        let variance = area(&self.squared_sum) as f64 / count - mean * mean;
+
+        (mean as f32, variance.max(0.0).sqrt() as f32)
+    }
+}
+
+/// Maximum (or minimum) over a sliding window of `2 * radius + 1` values
+/// using the van Herk/Gil-Werman algorithm: three comparisons per value
+/// regardless of the window size. Values past the ends are ignored.
+pub(crate) fn sliding_extremum(values: &[u8], radius: usize, max: bool) -> Vec<u8> {
+    // Any wider window already covers all the values.
+    let radius = radius.min(values.len());
+    let pick = |a: u8, b: u8| if max { a.max(b) } else { a.min(b) };
+    let neutral = if max { u8::MIN } else { u8::MAX };
+    let size = 2 * radius + 1;
+
+    let mut padded = vec![neutral; values.len() + 2 * radius];
+    padded[radius..radius + values.len()].copy_from_slice(values);
+    // Round up to whole blocks so every window finds its suffix and prefix.
+    padded.resize(padded.len().div_ceil(size) * size, neutral);
+
+    let mut prefix = padded.clone();
+    let mut suffix = padded;
+    for block in (0..prefix.len()).step_by(size) {
+        for i in block + 1..block + size {
+            prefix[i] = pick(prefix[i], prefix[i - 1]);
+        }
+        for i in (block..block + size - 1).rev() {
+            suffix[i] = pick(suffix[i], suffix[i + 1]);
+        }
+    }
+
+    (0..values.len())
+        .map(|i| pick(suffix[i], prefix[i + size - 1]))
+        .collect()
+}
+
+/// Separable 2D sliding maximum (or minimum) over a window with the given
+/// horizontal and vertical radius.
+pub(crate) fn window_extremum(
+    values: &[u8],
+    width: usize,
+    height: usize,
+    (horizontal, vertical): (usize, usize),
+    max: bool,
+) -> Vec<u8> {
+    let mut rows = Vec::with_capacity(values.len());
+    for row in values.chunks(width) {
+        rows.extend(sliding_extremum(row, horizontal, max));
+    }
+
+    let mut result = vec![0u8; values.len()];
+    let mut column = vec![0u8; height];
+    for x in 0..width {
+        for y in 0..height {
+            column[y] = rows[y * width + x];
+        }
+        for (y, value) in sliding_extremum(&column, vertical, max)
+            .into_iter()
+            .enumerate()
+        {
+            result[y * width + x] = value;
+        }
+    }
+
+    result
+}
+
+impl Image {
+    /// Binarizes with a threshold computed per pixel from the `window` x
+    /// `window` neighbourhood around it. Pixels above their threshold become
+    /// white, the rest black.
+    pub fn adaptive_threshold(&self, window: u32, method: AdaptiveMethod) -> Self {
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let radius = (window.max(1) / 2) as usize;
+        let gray: Vec<u8> = self.data.chunks(4).map(Self::luminance).collect();
+
+        let thresholds: Vec<f32> = match method {
+            AdaptiveMethod::Bernsen { contrast_limit } => {
+                let min = window_extremum(&gray, width, height, (radius, radius), false);
+                let max = window_extremum(&gray, width, height, (radius, radius), true);
+
+                min.iter()
+                    .zip(max.iter())
+                    .map(|(&min, &max)| {
+                        let mid = (min as f32 + max as f32) / 2.0;
+                        if max - min < contrast_limit {
+                            // Uniform neighbourhood, classify it as a whole.
+                            if mid >= 128.0 {
+                                -1.0
+                            } else {
+                                255.0
+                            }
+                        } else {
+                            mid
+                        }
+                    })
+                    .collect()
+            }
+            _ => {
+                let integral = IntegralImage::new(&gray, width, height);
+                let mut stats = Vec::with_capacity(gray.len());
+                for y in 0..height {
+                    let (y0, y1) = (y.saturating_sub(radius), (y + radius + 1).min(height));
+                    for x in 0..width {
+                        let (x0, x1) = (x.saturating_sub(radius), (x + radius + 1).min(width));
+                        stats.push(integral.stats((x0, y0), (x1, y1)));
+                    }
+                }
+
+                Self::local_thresholds(&gray, &stats, method)
+            }
+        };
+
+        let mut data = self.data.clone();
+        for (i, (&gray, &threshold)) in gray.iter().zip(thresholds.iter()).enumerate() {
+            let val = if gray as f32 > threshold { 255 } else { 0 };
+
+            for component in &[
+                ColorComponent::Red,
+                ColorComponent::Green,
+                ColorComponent::Blue,
+            ] {
+                data[(i * 4) + *component as usize] = val;
+            }
+        }
+
+        Self {
+            data,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    fn local_thresholds(gray: &[u8], stats: &[(f32, f32)], method: AdaptiveMethod) -> Vec<f32> {
+        match method {
+            AdaptiveMethod::Niblack { k } => stats.iter().map(|(m, s)| m + k * s).collect(),
+            AdaptiveMethod::Sauvola { k, r } => stats
+                .iter()
+                .map(|(m, s)| m * (1.0 + k * (s / r - 1.0)))
+                .collect(),
+            AdaptiveMethod::Wolf { k } => {
+                let darkest = gray.iter().copied().min().unwrap_or(0) as f32;
+                let max_deviation = stats
+                    .iter()
+                    .map(|(_, s)| *s)
+                    .fold(0.0f32, f32::max)
+                    .max(f32::EPSILON);
+
+                stats
+                    .iter()
+                    .map(|(m, s)| {
+                        (1.0 - k) * m + k * darkest + k * s / max_deviation * (m - darkest)
+                    })
+                    .collect()
+            }
+            AdaptiveMethod::Phansalkar { k, r, p, q } => stats
+                .iter()
+                .map(|(m, s)| {
+                    let (m, s) = (m / 255.0, s / 255.0);
+                    m * (1.0 + p * (-q * m).exp() + k * (s / r - 1.0)) * 255.0
+                })
+                .collect(),
+            AdaptiveMethod::Bernsen { .. } => unreachable!("Bernsen works on local extrema."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic values without the long monotonic runs a broken
+    /// prefix or suffix pass could still get right.
+    fn values(len: usize) -> Vec<u8> {
+        (0..len)
+            .map(|i| ((i * 7919 + i * i * 104_729) % 256) as u8)
+            .collect()
+    }
+
+    fn pick(window: impl Iterator<Item = u8>, max: bool) -> u8 {
+        if max {
+            window.max().unwrap()
+        } else {
+            window.min().unwrap()
+        }
+    }
+
+    #[test]
+    fn sliding_extremum_matches_brute_force() {
+        for len in [1, 2, 5, 17, 64] {
+            let values = values(len);
+            for radius in [0, 1, 2, 3, 7, 40] {
+                for max in [false, true] {
+                    let expected: Vec<u8> = (0..len)
+                        .map(|i| {
+                            let window = i.saturating_sub(radius)..(i + radius + 1).min(len);
+                            pick(values[window].iter().copied(), max)
+                        })
+                        .collect();
+
+                    assert_eq!(
+                        sliding_extremum(&values, radius, max),
+                        expected,
+                        "length {}, radius {}, max {}",
+                        len,
+                        radius,
+                        max
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sliding_extremum_clamps_huge_radii() {
+        let values = values(9);
+        for max in [false, true] {
+            assert_eq!(
+                sliding_extremum(&values, usize::MAX / 4, max),
+                vec![pick(values.iter().copied(), max); values.len()]
+            );
+        }
+    }
+
+    #[test]
+    fn window_extremum_matches_brute_force() {
+        let (width, height) = (13, 9);
+        let values = values(width * height);
+        for radii in [(0, 0), (1, 2), (3, 1), (6, 4), (20, 20)] {
+            for max in [false, true] {
+                let mut expected = Vec::with_capacity(values.len());
+                for y in 0..height {
+                    for x in 0..width {
+                        let rows = y.saturating_sub(radii.1)..(y + radii.1 + 1).min(height);
+                        let columns = x.saturating_sub(radii.0)..(x + radii.0 + 1).min(width);
+                        let window = rows
+                            .flat_map(|ny| columns.clone().map(move |nx| ny * width + nx))
+                            .map(|i| values[i]);
+                        expected.push(pick(window, max));
+                    }
+                }
+
+                assert_eq!(
+                    window_extremum(&values, width, height, radii, max),
+                    expected,
+                    "radii {:?}, max {}",
+                    radii,
+                    max
+                );
+            }
+        }
+    }
+}
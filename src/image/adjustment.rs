@@ -0,0 +1,175 @@
+use std::fmt;
+
+use super::{Image, Intensity, Levels};
+
+/// Basic intensity transforms, each a fixed mapping of gray levels.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Adjustment {
+    /// `Levels` with only its gamma changed.
+    Gamma(f32),
+    /// `brightness` is added after scaling the distance from mid-gray by
+    /// `1 + contrast / 100`, so a contrast of -100 gives a flat gray.
+    BrightnessContrast {
+        brightness: f32,
+        contrast: f32,
+    },
+    /// Expands the shadows, `strength` being the scale of the input under the
+    /// logarithm.
+    Log {
+        strength: f32,
+    },
+    /// Inverse of `Log`, expands the highlights.
+    Exp {
+        strength: f32,
+    },
+    Negative,
+    /// Inverts levels at or above `threshold`.
+    Solarize {
+        threshold: u8,
+    },
+    /// Reduces the image to `levels` evenly spaced gray levels.
+    Posterize {
+        levels: u8,
+    },
+}
+
+impl Adjustment {
+    pub const ALL: [Adjustment; 7] = [
+        Adjustment::Gamma(1.0),
+        Adjustment::BrightnessContrast {
+            brightness: 0.0,
+            contrast: 0.0,
+        },
+        Adjustment::Log { strength: 255.0 },
+        Adjustment::Exp { strength: 255.0 },
+        Adjustment::Negative,
+        Adjustment::Solarize { threshold: 128 },
+        Adjustment::Posterize { levels: 4 },
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Adjustment::Gamma(_) => "Gamma",
+            Adjustment::BrightnessContrast { .. } => "Brightness and contrast",
+            Adjustment::Log { .. } => "Logarithm",
+            Adjustment::Exp { .. } => "Exponential",
+            Adjustment::Negative => "Negative",
+            Adjustment::Solarize { .. } => "Solarize",
+            Adjustment::Posterize { .. } => "Posterize",
+        }
+    }
+
+    /// Names of the parameters `values` refers to, in the same order.
+    pub fn parameters(&self) -> &'static [&'static str] {
+        match self {
+            Adjustment::Gamma(_) => &["gamma"],
+            Adjustment::BrightnessContrast { .. } => &["brightness", "contrast"],
+            Adjustment::Log { .. } | Adjustment::Exp { .. } => &["strength"],
+            Adjustment::Negative => &[],
+            Adjustment::Solarize { .. } => &["threshold"],
+            Adjustment::Posterize { .. } => &["levels"],
+        }
+    }
+
+    /// Smallest and largest value of each parameter.
+    pub fn limits(&self) -> &'static [(f32, f32)] {
+        match self {
+            Adjustment::Gamma(_) => &[(0.1, 10.0)],
+            Adjustment::BrightnessContrast { .. } => &[(-255.0, 255.0), (-100.0, 100.0)],
+            Adjustment::Log { .. } | Adjustment::Exp { .. } => &[(1.0, 1000.0)],
+            Adjustment::Negative => &[],
+            Adjustment::Solarize { .. } => &[(0.0, 255.0)],
+            Adjustment::Posterize { .. } => &[(2.0, 255.0)],
+        }
+    }
+
+    pub fn values(&self) -> Vec<f32> {
+        match *self {
+            Adjustment::Gamma(gamma) => vec![gamma],
+            Adjustment::BrightnessContrast {
+                brightness,
+                contrast,
+            } => vec![brightness, contrast],
+            Adjustment::Log { strength } | Adjustment::Exp { strength } => vec![strength],
+            Adjustment::Negative => vec![],
+            Adjustment::Solarize { threshold } => vec![threshold as f32],
+            Adjustment::Posterize { levels } => vec![levels as f32],
+        }
+    }
+
+    /// Replaces the parameter at `index`, clamped to its limits. Out of
+    /// bounds indices leave the adjustment unchanged.
+    pub fn with_value(mut self, index: usize, value: f32) -> Self {
+        let value = match self.limits().get(index) {
+            Some((min, max)) => value.clamp(*min, *max),
+            None => return self,
+        };
+
+        match &mut self {
+            Adjustment::Gamma(gamma) => *gamma = value,
+            Adjustment::BrightnessContrast {
+                brightness,
+                contrast,
+            } => *[brightness, contrast][index] = value,
+            Adjustment::Log { strength } | Adjustment::Exp { strength } => *strength = value,
+            Adjustment::Negative => {}
+            Adjustment::Solarize { threshold } => *threshold = value.round() as u8,
+            Adjustment::Posterize { levels } => *levels = value.round() as u8,
+        }
+
+        self
+    }
+
+    pub fn lut(&self) -> [u8; 256] {
+        if let Adjustment::Gamma(gamma) = *self {
+            return Levels {
+                gamma,
+                ..Levels::default()
+            }
+            .lut();
+        }
+
+        let mut lut = [0; 256];
+        for (i, entry) in lut.iter_mut().enumerate() {
+            let x = i as f32 / 255.0;
+            let y = match *self {
+                Adjustment::Gamma(_) => unreachable!("shares the levels table"),
+                Adjustment::BrightnessContrast {
+                    brightness,
+                    contrast,
+                } => (x - 0.5) * (1.0 + contrast / 100.0) + 0.5 + brightness / 255.0,
+                Adjustment::Log { strength } => (1.0 + strength * x).ln() / (1.0 + strength).ln(),
+                Adjustment::Exp { strength } => ((1.0 + strength).powf(x) - 1.0) / strength,
+                Adjustment::Negative => 1.0 - x,
+                Adjustment::Solarize { threshold } if i >= threshold as usize => 1.0 - x,
+                Adjustment::Solarize { .. } => x,
+                Adjustment::Posterize { levels } => {
+                    let steps = (levels.max(2) - 1) as f32;
+                    (i * levels as usize / 256) as f32 / steps
+                }
+            };
+            *entry = (y * 255.0).round().clamp(0.0, 255.0) as u8;
+        }
+
+        lut
+    }
+}
+
+impl fmt::Display for Adjustment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())?;
+        for (parameter, value) in self.parameters().iter().zip(self.values()) {
+            write!(f, " {} {}", parameter, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Image {
+    pub fn adjust(&self, adjustment: Adjustment, intensity: Intensity) -> Self {
+        let lut = adjustment.lut();
+
+        self.map_intensity(intensity, |_, _, value| lut[value as usize])
+    }
+}
@@ -0,0 +1,125 @@
+use super::{Image, Intensity};
+
+/// Caps every bin of `histogram` at `limit` and spreads the clipped counts
+/// evenly over all bins, the remainder one count per bin at a regular step.
+fn clip_histogram(histogram: &mut [u32; 256], limit: u32) {
+    let mut excess = 0;
+    for count in histogram.iter_mut() {
+        if *count > limit {
+            excess += *count - limit;
+            *count = limit;
+        }
+    }
+
+    let increment = excess / 256;
+    let remainder = (excess % 256) as usize;
+    for count in histogram.iter_mut() {
+        *count += increment;
+    }
+    if let Some(step) = 256usize.checked_div(remainder) {
+        for i in (0..256).step_by(step).take(remainder) {
+            histogram[i] += 1;
+        }
+    }
+}
+
+/// Equalization mapping of a single tile, `pixels` being its pixel count.
+fn tile_lut(histogram: &[u32; 256], pixels: u32) -> [u8; 256] {
+    let mut lut = [0; 256];
+    let mut sum = 0;
+    for i in 0..256 {
+        sum += histogram[i];
+        lut[i] = (sum as f32 / pixels.max(1) as f32 * 255.0)
+            .round()
+            .min(255.0) as u8;
+    }
+
+    lut
+}
+
+/// CLAHE on a single plane of gray levels. Pixels between tile centers
+/// blend the mappings of the four nearest tiles bilinearly, pixels along the
+/// borders fall back to the nearest two or one.
+fn equalize_tiles(
+    plane: &[u8],
+    width: usize,
+    height: usize,
+    (columns, rows): (u32, u32),
+    clip_limit: f32,
+) -> Vec<u8> {
+    let tile_width = width
+        .div_ceil((columns as usize).clamp(1, width.max(1)))
+        .max(1);
+    let tile_height = height
+        .div_ceil((rows as usize).clamp(1, height.max(1)))
+        .max(1);
+    let columns = width.div_ceil(tile_width);
+    let rows = height.div_ceil(tile_height);
+
+    let mut luts = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            let (x0, y0) = (column * tile_width, row * tile_height);
+            let (x1, y1) = ((x0 + tile_width).min(width), (y0 + tile_height).min(height));
+            let pixels = ((x1 - x0) * (y1 - y0)) as u32;
+
+            let mut histogram = [0; 256];
+            for y in y0..y1 {
+                for &value in &plane[y * width + x0..y * width + x1] {
+                    histogram[value as usize] += 1;
+                }
+            }
+            if clip_limit > 0.0 {
+                let limit = (clip_limit * pixels as f32 / 256.0).max(1.0) as u32;
+                clip_histogram(&mut histogram, limit);
+            }
+            luts.push(tile_lut(&histogram, pixels));
+        }
+    }
+
+    // Index of the tile whose center precedes `position` and the weight of
+    // the one after it.
+    let neighbours = |position: usize, size: usize, count: usize| {
+        let center = (position as f32 + 0.5) / size as f32 - 0.5;
+        let first = center.floor().clamp(0.0, (count - 1) as f32);
+        let weight = (center - first).clamp(0.0, 1.0);
+        (first as usize, (first as usize + 1).min(count - 1), weight)
+    };
+
+    let mut result = vec![0; plane.len()];
+    for y in 0..height {
+        let (top, bottom, fy) = neighbours(y, tile_height, rows);
+        for x in 0..width {
+            let (left, right, fx) = neighbours(x, tile_width, columns);
+            let value = plane[y * width + x] as usize;
+            let map = |row: usize, column: usize| luts[row * columns + column][value] as f32;
+
+            let upper = map(top, left) * (1.0 - fx) + map(top, right) * fx;
+            let lower = map(bottom, left) * (1.0 - fx) + map(bottom, right) * fx;
+            result[y * width + x] = (upper * (1.0 - fy) + lower * fy).round() as u8;
+        }
+    }
+
+    result
+}
+
+impl Image {
+    /// Contrast-limited adaptive histogram equalization. The image is split
+    /// into a `columns` x `rows` grid of tiles which are equalized on their
+    /// own, with no histogram bin allowed above `clip_limit` times the mean
+    /// bin count so noise in flat regions isn't amplified. A `clip_limit` of
+    /// 0 or below disables clipping.
+    pub fn clahe(&self, grid: (u32, u32), clip_limit: f32, intensity: Intensity) -> Self {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let planes: Vec<Vec<u8>> = intensity
+            .channels()
+            .iter()
+            .map(|channel| {
+                let plane: Vec<u8> = self.data.chunks(4).map(|pixel| channel.of(pixel)).collect();
+                equalize_tiles(&plane, width, height, grid, clip_limit)
+            })
+            .collect();
+
+        self.map_intensity(intensity, |i, channel, _| planes[channel][i])
+    }
+}
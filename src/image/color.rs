@@ -0,0 +1,264 @@
+//! Conversions between 8-bit sRGB and other color spaces, and the channels
+//! of those spaces as gray levels.
+
+/// Reference white of the D65 illuminant, X and Z relative to Y = 1.
+const WHITE_X: f32 = 0.95047;
+const WHITE_Z: f32 = 1.08883;
+/// CIE constants for the linear segment of L*a*b* near black.
+const LAB_EPSILON: f32 = 216.0 / 24389.0;
+const LAB_KAPPA: f32 = 24389.0 / 27.0;
+
+fn to_byte(value: f32) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+/// Hue in degrees 0.0..360.0, chroma and the largest component, all from
+/// components in 0.0..=1.0.
+fn hue_chroma(red: u8, green: u8, blue: u8) -> (f32, f32, f32, f32) {
+    let (r, g, b) = (
+        red as f32 / 255.0,
+        green as f32 / 255.0,
+        blue as f32 / 255.0,
+    );
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let chroma = max - min;
+
+    let hue = if chroma == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / chroma + 2.0)
+    } else {
+        60.0 * ((r - g) / chroma + 4.0)
+    };
+
+    (hue, chroma, max, min)
+}
+
+/// Inverse of `hue_chroma`: the RGB color with the given hue and chroma
+/// whose components are offset by `offset`.
+fn from_hue_chroma(hue: f32, chroma: f32, offset: f32) -> (u8, u8, u8) {
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+
+    (
+        to_byte((r + offset) * 255.0),
+        to_byte((g + offset) * 255.0),
+        to_byte((b + offset) * 255.0),
+    )
+}
+
+/// Hue in degrees 0.0..360.0, saturation and value in 0.0..=1.0.
+pub fn rgb_to_hsv(red: u8, green: u8, blue: u8) -> (f32, f32, f32) {
+    let (hue, chroma, max, _) = hue_chroma(red, green, blue);
+    let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+
+    (hue, saturation, max)
+}
+
+pub fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> (u8, u8, u8) {
+    let chroma = value * saturation;
+
+    from_hue_chroma(hue, chroma, value - chroma)
+}
+
+/// Hue in degrees 0.0..360.0, saturation and lightness in 0.0..=1.0.
+pub fn rgb_to_hsl(red: u8, green: u8, blue: u8) -> (f32, f32, f32) {
+    let (hue, chroma, max, min) = hue_chroma(red, green, blue);
+    let lightness = (max + min) / 2.0;
+    let saturation = if chroma == 0.0 {
+        0.0
+    } else {
+        chroma / (1.0 - (2.0 * lightness - 1.0).abs())
+    };
+
+    (hue, saturation, lightness)
+}
+
+pub fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> (u8, u8, u8) {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+
+    from_hue_chroma(hue, chroma, lightness - chroma / 2.0)
+}
+
+/// CIE L*a*b* under the D65 white point, L* in 0.0..=100.0 and a*, b*
+/// roughly in -128.0..=127.0.
+pub fn rgb_to_lab(red: u8, green: u8, blue: u8) -> (f32, f32, f32) {
+    let linear = |value: u8| {
+        let value = value as f32 / 255.0;
+        if value <= 0.04045 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (linear(red), linear(green), linear(blue));
+
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / WHITE_X;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / WHITE_Z;
+
+    let f = |t: f32| {
+        if t > LAB_EPSILON {
+            t.cbrt()
+        } else {
+            (LAB_KAPPA * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+
+    (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+/// Inverse of `rgb_to_lab`, colors outside the sRGB gamut are clipped.
+pub fn lab_to_rgb(lightness: f32, a: f32, b: f32) -> (u8, u8, u8) {
+    let fy = (lightness + 16.0) / 116.0;
+    let fx = fy + a / 500.0;
+    let fz = fy - b / 200.0;
+
+    let f_inverse = |t: f32| {
+        if t.powi(3) > LAB_EPSILON {
+            t.powi(3)
+        } else {
+            (116.0 * t - 16.0) / LAB_KAPPA
+        }
+    };
+    let x = f_inverse(fx) * WHITE_X;
+    let y = f_inverse(fy);
+    let z = f_inverse(fz) * WHITE_Z;
+
+    let gamma = |value: f32| {
+        let value = if value <= 0.0031308 {
+            12.92 * value
+        } else {
+            1.055 * value.powf(1.0 / 2.4) - 0.055
+        };
+        to_byte(value * 255.0)
+    };
+
+    (
+        gamma(3.2406 * x - 1.5372 * y - 0.4986 * z),
+        gamma(-0.9689 * x + 1.8758 * y + 0.0415 * z),
+        gamma(0.0557 * x - 0.2040 * y + 1.0570 * z),
+    )
+}
+
+/// Full-range BT.601 YCbCr as used by JPEG, all components in
+/// 0.0..=255.0.
+pub fn rgb_to_ycbcr(red: u8, green: u8, blue: u8) -> (f32, f32, f32) {
+    let (r, g, b) = (red as f32, green as f32, blue as f32);
+
+    (
+        0.299 * r + 0.587 * g + 0.114 * b,
+        128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b,
+        128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b,
+    )
+}
+
+pub fn ycbcr_to_rgb(y: f32, cb: f32, cr: f32) -> (u8, u8, u8) {
+    (
+        to_byte(y + 1.402 * (cr - 128.0)),
+        to_byte(y - 0.344136 * (cb - 128.0) - 0.714136 * (cr - 128.0)),
+        to_byte(y + 1.772 * (cb - 128.0)),
+    )
+}
+
+/// A single component of a color space, read from an RGB pixel and scaled
+/// to a gray level.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    /// Luminance as counted by `get_grayscale_histogram`.
+    Luminance,
+    /// Shared by HSV and HSL, 0..360° mapped to 0..=255.
+    Hue,
+    HsvSaturation,
+    Value,
+    HslSaturation,
+    HslLightness,
+    /// L* mapped from 0..=100 to 0..=255.
+    LabLightness,
+    /// a* offset by 128.
+    LabA,
+    /// b* offset by 128.
+    LabB,
+    Luma,
+    Cb,
+    Cr,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 15] = [
+        Channel::Red,
+        Channel::Green,
+        Channel::Blue,
+        Channel::Luminance,
+        Channel::Hue,
+        Channel::HsvSaturation,
+        Channel::Value,
+        Channel::HslSaturation,
+        Channel::HslLightness,
+        Channel::LabLightness,
+        Channel::LabA,
+        Channel::LabB,
+        Channel::Luma,
+        Channel::Cb,
+        Channel::Cr,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Channel::Red => "Red",
+            Channel::Green => "Green",
+            Channel::Blue => "Blue",
+            Channel::Luminance => "Luminance",
+            Channel::Hue => "Hue",
+            Channel::HsvSaturation => "Saturation (HSV)",
+            Channel::Value => "Value (HSV)",
+            Channel::HslSaturation => "Saturation (HSL)",
+            Channel::HslLightness => "Lightness (HSL)",
+            Channel::LabLightness => "L* (Lab)",
+            Channel::LabA => "a* (Lab)",
+            Channel::LabB => "b* (Lab)",
+            Channel::Luma => "Y (YCbCr)",
+            Channel::Cb => "Cb (YCbCr)",
+            Channel::Cr => "Cr (YCbCr)",
+        }
+    }
+
+    /// Value of the channel for an RGB(A) pixel.
+    pub fn of(&self, pixel: &[u8]) -> u8 {
+        let (r, g, b) = (pixel[0], pixel[1], pixel[2]);
+        let unit = |value: f32| to_byte(value * 255.0);
+
+        match self {
+            Channel::Red => r,
+            Channel::Green => g,
+            Channel::Blue => b,
+            Channel::Luminance => super::Image::luminance(pixel),
+            Channel::Hue => to_byte(rgb_to_hsv(r, g, b).0 / 360.0 * 255.0),
+            Channel::HsvSaturation => unit(rgb_to_hsv(r, g, b).1),
+            Channel::Value => unit(rgb_to_hsv(r, g, b).2),
+            Channel::HslSaturation => unit(rgb_to_hsl(r, g, b).1),
+            Channel::HslLightness => unit(rgb_to_hsl(r, g, b).2),
+            Channel::LabLightness => to_byte(rgb_to_lab(r, g, b).0 / 100.0 * 255.0),
+            Channel::LabA => to_byte(rgb_to_lab(r, g, b).1 + 128.0),
+            Channel::LabB => to_byte(rgb_to_lab(r, g, b).2 + 128.0),
+            Channel::Luma => to_byte(rgb_to_ycbcr(r, g, b).0),
+            Channel::Cb => to_byte(rgb_to_ycbcr(r, g, b).1),
+            Channel::Cr => to_byte(rgb_to_ycbcr(r, g, b).2),
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use super::{morphology::flat_extremum, Image, Intensity, StructuringElement};
+
+/// Operations on gray levels: erosion takes the darkest level under the
+/// element, dilation the brightest. Non-flat elements lower the levels
+/// they reach by their height.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GrayMorphology {
+    Erode,
+    Dilate,
+    /// Erosion then dilation, removes bright details smaller than the
+    /// element. With a large element this estimates the background under
+    /// dark text.
+    Open,
+    /// Dilation then erosion, removes dark details smaller than the element.
+    Close,
+    /// Image minus its opening, bright details on black.
+    TopHat,
+    /// Closing minus the image, dark details such as text turned bright on
+    /// black. A negative of it is the image on a flattened background.
+    BlackHat,
+    /// Dilation minus erosion, bright along edges.
+    Gradient,
+}
+
+impl GrayMorphology {
+    pub const ALL: [GrayMorphology; 7] = [
+        GrayMorphology::Erode,
+        GrayMorphology::Dilate,
+        GrayMorphology::Open,
+        GrayMorphology::Close,
+        GrayMorphology::TopHat,
+        GrayMorphology::BlackHat,
+        GrayMorphology::Gradient,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GrayMorphology::Erode => "Erode",
+            GrayMorphology::Dilate => "Dilate",
+            GrayMorphology::Open => "Open",
+            GrayMorphology::Close => "Close",
+            GrayMorphology::TopHat => "Top-hat",
+            GrayMorphology::BlackHat => "Black-hat",
+            GrayMorphology::Gradient => "Morphological gradient",
+        }
+    }
+}
+
+/// Minimum of `plane` under `element`, or maximum if `max` is set. Flat
+/// predefined shapes go through `flat_extremum`, the ball and custom
+/// elements take time proportional to their area per pixel.
+fn extremum(
+    plane: &[u8],
+    width: usize,
+    height: usize,
+    element: &StructuringElement,
+    max: bool,
+) -> Vec<u8> {
+    if let Some(result) = flat_extremum(plane, width, height, element, max) {
+        return result;
+    }
+
+    // Dilation probes the reflected element raised by its heights, erosion
+    // the element lowered by them.
+    let cells: Vec<((isize, isize), f32)> = element
+        .heights()
+        .into_iter()
+        .map(|((dx, dy), h)| if max { ((-dx, -dy), h) } else { ((dx, dy), -h) })
+        .collect();
+
+    let mut result = Vec::with_capacity(plane.len());
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let mut extremum = if max { f32::MIN } else { f32::MAX };
+            for ((dx, dy), h) in &cells {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                    continue;
+                }
+                let value = plane[ny as usize * width + nx as usize] as f32 + h;
+                extremum = if max {
+                    extremum.max(value)
+                } else {
+                    extremum.min(value)
+                };
+            }
+            result.push(extremum.round().clamp(0.0, 255.0) as u8);
+        }
+    }
+
+    result
+}
+
+fn difference(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(a, b)| a.saturating_sub(*b)).collect()
+}
+
+impl Image {
+    pub fn gray_morphology(
+        &self,
+        operation: GrayMorphology,
+        element: &StructuringElement,
+        intensity: Intensity,
+    ) -> Self {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let erode = |plane: &[u8]| extremum(plane, width, height, element, false);
+        let dilate = |plane: &[u8]| extremum(plane, width, height, element, true);
+
+        let planes: Vec<Vec<u8>> = intensity
+            .channels()
+            .iter()
+            .map(|channel| {
+                let plane: Vec<u8> = self.data.chunks(4).map(|pixel| channel.of(pixel)).collect();
+                match operation {
+                    GrayMorphology::Erode => erode(&plane),
+                    GrayMorphology::Dilate => dilate(&plane),
+                    GrayMorphology::Open => dilate(&erode(&plane)),
+                    GrayMorphology::Close => erode(&dilate(&plane)),
+                    GrayMorphology::TopHat => difference(&plane, &dilate(&erode(&plane))),
+                    GrayMorphology::BlackHat => difference(&erode(&dilate(&plane)), &plane),
+                    GrayMorphology::Gradient => difference(&dilate(&plane), &erode(&plane)),
+                }
+            })
+            .collect();
+
+        self.map_intensity(intensity, |i, channel, _| planes[channel][i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 19;
+    const HEIGHT: usize = 14;
+
+    fn plane() -> Vec<u8> {
+        (0..WIDTH * HEIGHT)
+            .map(|i| ((i * 7919 + i * i * 31) % 256) as u8)
+            .collect()
+    }
+
+    fn image(plane: &[u8], width: usize) -> Image {
+        Image {
+            width: width as u32,
+            height: (plane.len() / width) as u32,
+            data: plane.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        }
+    }
+
+    fn levels(image: &Image) -> Vec<u8> {
+        image.data.chunks(4).map(|pixel| pixel[0]).collect()
+    }
+
+    #[test]
+    fn flat_shapes_match_a_scan_of_their_cells() {
+        let plane = plane();
+        let elements = [
+            StructuringElement::Square(2),
+            StructuringElement::Rectangle(1, 3),
+            StructuringElement::Disk(1),
+            StructuringElement::Disk(4),
+            StructuringElement::Cross(3),
+        ];
+        for element in &elements {
+            for max in [false, true] {
+                let offsets = element.offsets(true);
+                let mut expected = Vec::with_capacity(plane.len());
+                for y in 0..HEIGHT as isize {
+                    for x in 0..WIDTH as isize {
+                        let window = offsets
+                            .iter()
+                            .map(|&(dx, dy)| {
+                                if max {
+                                    (x - dx, y - dy)
+                                } else {
+                                    (x + dx, y + dy)
+                                }
+                            })
+                            .filter(|&(nx, ny)| {
+                                nx >= 0 && ny >= 0 && nx < WIDTH as isize && ny < HEIGHT as isize
+                            })
+                            .map(|(nx, ny)| plane[ny as usize * WIDTH + nx as usize]);
+                        expected.push(if max {
+                            window.max().unwrap()
+                        } else {
+                            window.min().unwrap()
+                        });
+                    }
+                }
+
+                assert_eq!(
+                    extremum(&plane, WIDTH, HEIGHT, element, max),
+                    expected,
+                    "{}, max {}",
+                    element,
+                    max
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn elements_larger_than_the_image_cover_all_of_it() {
+        let plane = plane();
+        let (darkest, brightest) = (*plane.iter().min().unwrap(), *plane.iter().max().unwrap());
+        for element in [
+            StructuringElement::Square(u32::MAX),
+            StructuringElement::Rectangle(3_000_000_000, 3_000_000_000),
+            StructuringElement::Disk(u32::MAX),
+        ] {
+            assert_eq!(
+                extremum(&plane, WIDTH, HEIGHT, &element, false),
+                vec![darkest; plane.len()],
+                "{}",
+                element
+            );
+            assert_eq!(
+                extremum(&plane, WIDTH, HEIGHT, &element, true),
+                vec![brightest; plane.len()],
+                "{}",
+                element
+            );
+        }
+    }
+
+    #[test]
+    fn opening_and_closing_bracket_the_image() {
+        let plane = plane();
+        let image = image(&plane, WIDTH);
+        for element in [
+            StructuringElement::Square(1),
+            StructuringElement::Disk(2),
+            StructuringElement::Ball(3),
+            StructuringElement::Ball(6),
+        ] {
+            let open = levels(&image.gray_morphology(
+                GrayMorphology::Open,
+                &element,
+                Intensity::PerChannel,
+            ));
+            let close = levels(&image.gray_morphology(
+                GrayMorphology::Close,
+                &element,
+                Intensity::PerChannel,
+            ));
+
+            for i in 0..plane.len() {
+                assert!(open[i] <= plane[i] && plane[i] <= close[i], "{}", element);
+            }
+        }
+    }
+
+    #[test]
+    fn opening_and_closing_are_idempotent() {
+        let image = image(&plane(), WIDTH);
+        for element in [
+            StructuringElement::Square(1),
+            StructuringElement::Disk(2),
+            StructuringElement::Cross(2),
+        ] {
+            for operation in [GrayMorphology::Open, GrayMorphology::Close] {
+                let once = image.gray_morphology(operation, &element, Intensity::PerChannel);
+                let twice = once.gray_morphology(operation, &element, Intensity::PerChannel);
+                assert!(once == twice, "{} with {}", operation.name(), element);
+            }
+        }
+    }
+
+    #[test]
+    fn ball_dilation_falls_off_with_its_height() {
+        let mut plane = vec![0; 7 * 7];
+        plane[3 * 7 + 3] = 200;
+        let dilated = image(&plane, 7).gray_morphology(
+            GrayMorphology::Dilate,
+            &StructuringElement::Ball(3),
+            Intensity::PerChannel,
+        );
+
+        // 200 + sqrt(9 - d^2) - 3 at distance d from the peak.
+        assert_eq!(
+            levels(&dilated)[3 * 7..4 * 7],
+            [197, 199, 200, 200, 200, 199, 197]
+        );
+    }
+}
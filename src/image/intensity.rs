@@ -0,0 +1,236 @@
+use super::{
+    color::{hsv_to_rgb, lab_to_rgb, rgb_to_hsv, rgb_to_lab, rgb_to_ycbcr, ycbcr_to_rgb},
+    Channel, Image,
+};
+
+/// Which values histogram normalization remaps. Everything but `PerChannel`
+/// remaps a single brightness channel and keeps the chromatic components, so
+/// hues survive.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Intensity {
+    /// Red, green and blue independently.
+    PerChannel,
+    /// Y of YCbCr.
+    Luma,
+    /// L* of CIE L*a*b*.
+    LabLightness,
+    /// V of HSV.
+    Value,
+}
+
+impl Intensity {
+    pub const ALL: [Intensity; 4] = [
+        Intensity::PerChannel,
+        Intensity::Luma,
+        Intensity::LabLightness,
+        Intensity::Value,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Intensity::PerChannel => "R, G and B",
+            Intensity::Luma => "Y (YCbCr)",
+            Intensity::LabLightness => "L* (Lab)",
+            Intensity::Value => "V (HSV)",
+        }
+    }
+
+    fn channel(&self) -> Option<Channel> {
+        match self {
+            Intensity::PerChannel => None,
+            Intensity::Luma => Some(Channel::Luma),
+            Intensity::LabLightness => Some(Channel::LabLightness),
+            Intensity::Value => Some(Channel::Value),
+        }
+    }
+
+    /// Channels `Image::map_intensity` hands to its closure, by index.
+    pub(super) fn channels(&self) -> &'static [Channel] {
+        match self {
+            Intensity::PerChannel => &[Channel::Red, Channel::Green, Channel::Blue],
+            Intensity::Luma => &[Channel::Luma],
+            Intensity::LabLightness => &[Channel::LabLightness],
+            Intensity::Value => &[Channel::Value],
+        }
+    }
+}
+
+/// Maps each level to the cumulative histogram scaled onto the full range,
+/// the formula `get_equalized_image` has always used.
+pub(super) fn equalization_lut(histogram: &[u32; 256]) -> [u8; 256] {
+    let pixels: u32 = histogram.iter().sum();
+    // The smallest value of the cumulative histogram is its first.
+    let min = histogram[0];
+    let down = (pixels - min).max(1) as f32;
+
+    let mut lut = [0; 256];
+    let mut sum = 0;
+    for (entry, count) in lut.iter_mut().zip(histogram) {
+        sum += count;
+        *entry = ((sum - min) as f32 / down * 255.0).round() as u8;
+    }
+
+    lut
+}
+
+/// Lowest and highest gray level left once a `low` and a `high` fraction of
+/// the pixels are cut off at either end. With nothing cut off these are the
+/// first and last non-empty bins.
+fn clipped_range(histogram: &[u32; 256], (low, high): (f32, f32)) -> (u8, u8) {
+    let pixels: u32 = histogram.iter().sum();
+    let cut_low = (low as f64 * pixels as f64) as u32;
+    let cut_high = (high as f64 * pixels as f64) as u32;
+
+    let mut sum = 0;
+    let min = histogram
+        .iter()
+        .position(|&count| {
+            sum += count;
+            sum > cut_low
+        })
+        .unwrap_or(0);
+    sum = 0;
+    let max = histogram
+        .iter()
+        .rposition(|&count| {
+            sum += count;
+            sum > cut_high
+        })
+        .unwrap_or(255);
+
+    (min as u8, max.max(min) as u8)
+}
+
+/// Maps `min..=max` linearly onto the full range, saturating outside it.
+pub(super) fn range_lut((min, max): (u8, u8)) -> [u8; 256] {
+    let (min, max) = (min as usize, max as usize);
+    let range = (max - min).max(1) as f32;
+
+    let mut lut = [0; 256];
+    for (i, entry) in lut.iter_mut().enumerate() {
+        *entry = (i.saturating_sub(min) as f32 / range * 255.0).min(255.0) as u8;
+    }
+
+    lut
+}
+
+pub(super) fn stretch_lut(histogram: &[u32; 256]) -> [u8; 256] {
+    range_lut(clipped_range(histogram, (0.0, 0.0)))
+}
+
+/// `Image::clipped_ranges` for the given channel histograms.
+pub(super) fn clipped_ranges(
+    histograms: &[[u32; 256]],
+    clip: (f32, f32),
+    linked: bool,
+) -> Vec<(u8, u8)> {
+    if linked {
+        let mut combined = [0; 256];
+        for histogram in histograms {
+            for i in 0..256 {
+                combined[i] += histogram[i];
+            }
+        }
+        vec![clipped_range(&combined, clip); histograms.len()]
+    } else {
+        histograms
+            .iter()
+            .map(|histogram| clipped_range(histogram, clip))
+            .collect()
+    }
+}
+
+impl Image {
+    pub fn equalize_intensity(&self, intensity: Intensity) -> Self {
+        match intensity.channel() {
+            Some(channel) => {
+                let lut = equalization_lut(&self.get_channel_histogram(channel));
+                self.map_intensity(intensity, |_, _, value| lut[value as usize])
+            }
+            None => self.get_equalized_image(),
+        }
+    }
+
+    pub fn stretch_intensity(&self, intensity: Intensity) -> Self {
+        match intensity.channel() {
+            Some(channel) => {
+                let lut = stretch_lut(&self.get_channel_histogram(channel));
+                self.map_intensity(intensity, |_, _, value| lut[value as usize])
+            }
+            None => self.get_stretched_image(),
+        }
+    }
+
+    /// Input range `stretch_clipped` maps onto 0..=255 for each channel of
+    /// `intensity`. `clip` holds the fractions of pixels saturated to black
+    /// and to white. Linked channels share a range taken from their combined
+    /// histogram, which keeps the color balance.
+    pub fn clipped_ranges(
+        &self,
+        clip: (f32, f32),
+        intensity: Intensity,
+        linked: bool,
+    ) -> Vec<(u8, u8)> {
+        let histograms: Vec<[u32; 256]> = intensity
+            .channels()
+            .iter()
+            .map(|&channel| self.get_channel_histogram(channel))
+            .collect();
+
+        clipped_ranges(&histograms, clip, linked)
+    }
+
+    /// Stretches the histogram like `stretch_intensity`, but ignores
+    /// outliers at either end, see `clipped_ranges`.
+    pub fn stretch_clipped(&self, clip: (f32, f32), intensity: Intensity, linked: bool) -> Self {
+        let luts: Vec<[u8; 256]> = self
+            .clipped_ranges(clip, intensity, linked)
+            .into_iter()
+            .map(range_lut)
+            .collect();
+
+        self.map_intensity(intensity, |_, channel, value| luts[channel][value as usize])
+    }
+
+    /// Replaces the channels of `intensity` with `map(pixel index, index in
+    /// `Intensity::channels`, value)` and converts back to RGB. Values use the
+    /// same 8-bit quantization as `Channel`.
+    pub(super) fn map_intensity(
+        &self,
+        intensity: Intensity,
+        map: impl Fn(usize, usize, u8) -> u8,
+    ) -> Self {
+        let map_scaled = |i: usize, value: f32, scale: f32| {
+            let value = (value / scale * 255.0).round().clamp(0.0, 255.0) as u8;
+            map(i, 0, value) as f32 / 255.0 * scale
+        };
+
+        let mut data = self.data.clone();
+        for (i, pixel) in self.data.chunks(4).enumerate() {
+            let (r, g, b) = (pixel[0], pixel[1], pixel[2]);
+            let (r, g, b) = match intensity {
+                Intensity::PerChannel => (map(i, 0, r), map(i, 1, g), map(i, 2, b)),
+                Intensity::Luma => {
+                    let (y, cb, cr) = rgb_to_ycbcr(r, g, b);
+                    ycbcr_to_rgb(map_scaled(i, y, 255.0), cb, cr)
+                }
+                Intensity::LabLightness => {
+                    let (l, a, b) = rgb_to_lab(r, g, b);
+                    lab_to_rgb(map_scaled(i, l, 100.0), a, b)
+                }
+                Intensity::Value => {
+                    let (h, s, v) = rgb_to_hsv(r, g, b);
+                    hsv_to_rgb(h, s, map_scaled(i, v, 1.0))
+                }
+            };
+
+            data[i * 4..i * 4 + 3].copy_from_slice(&[r, g, b]);
+        }
+
+        Self {
+            data,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use super::{
+    intensity::{clipped_ranges, equalization_lut, range_lut, stretch_lut},
+    matching::matching_lut,
+    Channel, HistogramTarget, Image,
+};
+
+/// Red, green and blue histograms in that order.
+pub type RgbHistograms = [[u32; 256]; 3];
+
+/// Lookup tables for red, green and blue, a point operation on each channel
+/// on its own. Alpha is never touched.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Lut {
+    tables: [[u8; 256]; 3],
+}
+
+impl Default for Lut {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Lut {
+    pub fn identity() -> Self {
+        let mut table = [0; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            *entry = i as u8;
+        }
+
+        Self::uniform(table)
+    }
+
+    /// The same table for all three channels.
+    pub fn uniform(table: [u8; 256]) -> Self {
+        Self {
+            tables: [table; 3],
+        }
+    }
+
+    pub fn per_channel(tables: [[u8; 256]; 3]) -> Self {
+        Self { tables }
+    }
+
+    /// `get_equalized_image` for the given histograms.
+    pub fn equalization(histograms: &RgbHistograms) -> Self {
+        Self::per_channel(histograms.map(|histogram| equalization_lut(&histogram)))
+    }
+
+    /// `get_stretched_image` for the given histograms.
+    pub fn stretch(histograms: &RgbHistograms) -> Self {
+        Self::per_channel(histograms.map(|histogram| stretch_lut(&histogram)))
+    }
+
+    /// `stretch_clipped` on red, green and blue for the given histograms.
+    pub fn clipped_stretch(histograms: &RgbHistograms, clip: (f32, f32), linked: bool) -> Self {
+        let ranges = clipped_ranges(histograms, clip, linked);
+
+        Self::per_channel([
+            range_lut(ranges[0]),
+            range_lut(ranges[1]),
+            range_lut(ranges[2]),
+        ])
+    }
+
+    /// `match_histogram` on red, green and blue for the given histograms.
+    pub fn histogram_match(histograms: &RgbHistograms, target: &HistogramTarget) -> Self {
+        let channels = [Channel::Red, Channel::Green, Channel::Blue];
+
+        Self::per_channel([0, 1, 2].map(|i| {
+            matching_lut(&histograms[i], &target.distribution(channels[i]))
+        }))
+    }
+
+    /// Table applying `self` and then `next`.
+    pub fn then(&self, next: &Lut) -> Self {
+        let mut tables = [[0; 256]; 3];
+        for (c, table) in tables.iter_mut().enumerate() {
+            for (entry, level) in table.iter_mut().zip(self.tables[c]) {
+                *entry = next.tables[c][level as usize];
+            }
+        }
+
+        Self { tables }
+    }
+
+    /// Table undoing `self`, if every table is strictly increasing or
+    /// decreasing. Over 256 levels that maps each level to a different one.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut tables = [[0; 256]; 3];
+        for (inverse, table) in tables.iter_mut().zip(&self.tables) {
+            let increasing = table.windows(2).all(|pair| pair[0] < pair[1]);
+            let decreasing = table.windows(2).all(|pair| pair[0] > pair[1]);
+            if !increasing && !decreasing {
+                return None;
+            }
+            for (i, &level) in table.iter().enumerate() {
+                inverse[level as usize] = i as u8;
+            }
+        }
+
+        Some(Self { tables })
+    }
+
+    /// Histograms of an image with `histograms` once `self` is applied.
+    pub fn map_histograms(&self, histograms: &RgbHistograms) -> RgbHistograms {
+        let mut mapped = [[0; 256]; 3];
+        for c in 0..3 {
+            for i in 0..256 {
+                mapped[c][self.tables[c][i] as usize] += histograms[c][i];
+            }
+        }
+
+        mapped
+    }
+}
+
+impl Image {
+    pub fn apply_lut(&self, lut: &Lut) -> Self {
+        let mut data = self.data.clone();
+        for pixel in data.chunks_exact_mut(4) {
+            pixel[0] = lut.tables[0][pixel[0] as usize];
+            pixel[1] = lut.tables[1][pixel[1] as usize];
+            pixel[2] = lut.tables[2][pixel[2] as usize];
+        }
+
+        Self {
+            data,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    pub fn get_rgb_histograms(&self) -> RgbHistograms {
+        let mut histograms = [[0; 256]; 3];
+        for pixel in self.data.chunks_exact(4) {
+            histograms[0][pixel[0] as usize] += 1;
+            histograms[1][pixel[1] as usize] += 1;
+            histograms[2][pixel[2] as usize] += 1;
+        }
+
+        histograms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Channels with different ranges and gaps so each gets its own table.
+    fn image() -> Image {
+        let (width, height) = (23, 17);
+        let data = (0..(width * height) as usize)
+            .flat_map(|i| {
+                [
+                    (i * 37 % 256) as u8,
+                    (i * i * 11 % 200 + 30) as u8,
+                    (i * 5 % 64 + 100) as u8,
+                    255,
+                ]
+            })
+            .collect();
+
+        Image {
+            width,
+            height,
+            data,
+        }
+    }
+
+    #[test]
+    fn map_histograms_predicts_the_mapped_image() {
+        let image = image();
+        let histograms = image.get_rgb_histograms();
+        let mut negative = [0; 256];
+        for (i, entry) in negative.iter_mut().enumerate() {
+            *entry = 255 - i as u8;
+        }
+
+        for lut in [
+            Lut::equalization(&histograms),
+            Lut::stretch(&histograms),
+            Lut::clipped_stretch(&histograms, (0.05, 0.05), false),
+            Lut::uniform(negative),
+        ] {
+            assert_eq!(
+                lut.map_histograms(&histograms),
+                image.apply_lut(&lut).get_rgb_histograms()
+            );
+        }
+    }
+
+    #[test]
+    fn inverse_undoes_one_to_one_tables() {
+        let mut negative = [0; 256];
+        for (i, entry) in negative.iter_mut().enumerate() {
+            *entry = 255 - i as u8;
+        }
+        let negative = Lut::uniform(negative);
+        assert_eq!(negative.inverse(), Some(negative));
+
+        let mixed = Lut::per_channel([
+            negative.tables[0],
+            Lut::identity().tables[1],
+            negative.tables[2],
+        ]);
+        assert_eq!(mixed.then(&mixed.inverse().unwrap()), Lut::identity());
+        assert_eq!(Lut::identity().inverse(), Some(Lut::identity()));
+    }
+
+    #[test]
+    fn inverse_needs_strictly_monotonic_tables() {
+        let histograms = image().get_rgb_histograms();
+        assert_eq!(
+            Lut::clipped_stretch(&histograms, (0.05, 0.05), false).inverse(),
+            None
+        );
+
+        // One to one, but not monotonic.
+        let mut swapped = Lut::identity().tables[0];
+        swapped.swap(10, 20);
+        assert_eq!(Lut::uniform(swapped).inverse(), None);
+    }
+
+    #[test]
+    fn then_applies_both_tables() {
+        let image = image();
+        let histograms = image.get_rgb_histograms();
+        let first = Lut::stretch(&histograms);
+        let second = Lut::equalization(&first.map_histograms(&histograms));
+
+        assert!(
+            image.apply_lut(&first.then(&second)) == image.apply_lut(&first).apply_lut(&second)
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use std::fmt;
+
+use super::{Channel, Image, Intensity};
+
+/// Histogram `Image::match_histogram` reshapes an image to.
+#[derive(Clone, PartialEq, Debug)]
+pub enum HistogramTarget {
+    /// Histograms of a reference image for every channel an `Intensity` can
+    /// remap, see `HistogramTarget::reference`.
+    Reference(Vec<(Channel, [u32; 256])>),
+    /// Normal distribution, both parameters in gray levels.
+    Gaussian { mean: f32, deviation: f32 },
+    /// Exponential distribution falling off from black, `rate` per gray level.
+    Exponential { rate: f32 },
+    /// Rayleigh distribution peaking at `scale` gray levels.
+    Rayleigh { scale: f32 },
+}
+
+impl HistogramTarget {
+    /// Parametric targets with their default parameters.
+    pub const ALL: [HistogramTarget; 3] = [
+        HistogramTarget::Gaussian {
+            mean: 128.0,
+            deviation: 40.0,
+        },
+        HistogramTarget::Exponential { rate: 0.02 },
+        HistogramTarget::Rayleigh { scale: 80.0 },
+    ];
+
+    pub fn reference(image: &Image) -> Self {
+        let histograms = Intensity::ALL
+            .iter()
+            .flat_map(|intensity| intensity.channels())
+            .map(|&channel| (channel, image.get_channel_histogram(channel)))
+            .collect();
+
+        HistogramTarget::Reference(histograms)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HistogramTarget::Reference(_) => "Reference image",
+            HistogramTarget::Gaussian { .. } => "Gaussian",
+            HistogramTarget::Exponential { .. } => "Exponential",
+            HistogramTarget::Rayleigh { .. } => "Rayleigh",
+        }
+    }
+
+    /// Names of the parameters `values` refers to, in the same order.
+    pub fn parameters(&self) -> &'static [&'static str] {
+        match self {
+            HistogramTarget::Reference(_) => &[],
+            HistogramTarget::Gaussian { .. } => &["mean", "deviation"],
+            HistogramTarget::Exponential { .. } => &["rate"],
+            HistogramTarget::Rayleigh { .. } => &["scale"],
+        }
+    }
+
+    /// Smallest and largest value of each parameter.
+    pub fn limits(&self) -> &'static [(f32, f32)] {
+        match self {
+            HistogramTarget::Reference(_) => &[],
+            HistogramTarget::Gaussian { .. } => &[(0.0, 255.0), (1.0, 255.0)],
+            HistogramTarget::Exponential { .. } => &[(0.001, 1.0)],
+            HistogramTarget::Rayleigh { .. } => &[(1.0, 255.0)],
+        }
+    }
+
+    pub fn values(&self) -> Vec<f32> {
+        match *self {
+            HistogramTarget::Reference(_) => vec![],
+            HistogramTarget::Gaussian { mean, deviation } => vec![mean, deviation],
+            HistogramTarget::Exponential { rate } => vec![rate],
+            HistogramTarget::Rayleigh { scale } => vec![scale],
+        }
+    }
+
+    /// Replaces the parameter at `index`, clamped to its limits. Out of
+    /// bounds indices leave the target unchanged.
+    pub fn with_value(mut self, index: usize, value: f32) -> Self {
+        let value = match self.limits().get(index) {
+            Some((min, max)) => value.clamp(*min, *max),
+            None => return self,
+        };
+
+        match &mut self {
+            HistogramTarget::Reference(_) => {}
+            HistogramTarget::Gaussian { mean, deviation } => *[mean, deviation][index] = value,
+            HistogramTarget::Exponential { rate } => *rate = value,
+            HistogramTarget::Rayleigh { scale } => *scale = value,
+        }
+
+        self
+    }
+
+    /// Relative frequency of every gray level of `channel`, not normalized.
+    pub(super) fn distribution(&self, channel: Channel) -> [f64; 256] {
+        let mut distribution = [0.0; 256];
+        for i in 0..256 {
+            let x = i as f64;
+            distribution[i] = match *self {
+                HistogramTarget::Reference(ref histograms) => histograms
+                    .iter()
+                    .find(|(reference_channel, _)| *reference_channel == channel)
+                    .map_or(1.0, |(_, histogram)| histogram[i] as f64),
+                HistogramTarget::Gaussian { mean, deviation } => {
+                    let z = (x - mean as f64) / deviation as f64;
+                    (-z * z / 2.0).exp()
+                }
+                HistogramTarget::Exponential { rate } => (-rate as f64 * x).exp(),
+                HistogramTarget::Rayleigh { scale } => {
+                    let scale = scale as f64;
+                    x / (scale * scale) * (-x * x / (2.0 * scale * scale)).exp()
+                }
+            };
+        }
+
+        distribution
+    }
+}
+
+impl fmt::Display for HistogramTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())?;
+        for (parameter, value) in self.parameters().iter().zip(self.values()) {
+            write!(f, " {} {}", parameter, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn cumulative(distribution: impl Iterator<Item = f64>) -> [f64; 256] {
+    let mut cdf = [0.0; 256];
+    let mut sum = 0.0;
+    for (i, value) in distribution.enumerate().take(256) {
+        sum += value;
+        cdf[i] = sum;
+    }
+
+    let total = if sum > 0.0 { sum } else { 1.0 };
+    cdf.map(|value| value / total)
+}
+
+/// Maps every gray level to the lowest target level whose cumulative
+/// frequency reaches that of the source level.
+pub(super) fn matching_lut(source: &[u32; 256], target: &[f64; 256]) -> [u8; 256] {
+    let source_cdf = cumulative(source.iter().map(|&count| count as f64));
+    let target_cdf = cumulative(target.iter().copied());
+
+    let mut lut = [0; 256];
+    let mut level = 0;
+    for i in 0..256 {
+        while level < 255 && target_cdf[level] < source_cdf[i] - 1e-9 {
+            level += 1;
+        }
+        lut[i] = level as u8;
+    }
+
+    lut
+}
+
+impl Image {
+    /// Remaps the channels of `intensity` so their histograms follow
+    /// `target`, like `equalize_intensity` does for a uniform target.
+    pub fn match_histogram(&self, target: &HistogramTarget, intensity: Intensity) -> Self {
+        let luts: Vec<[u8; 256]> = intensity
+            .channels()
+            .iter()
+            .map(|&channel| {
+                matching_lut(
+                    &self.get_channel_histogram(channel),
+                    &target.distribution(channel),
+                )
+            })
+            .collect();
+
+        self.map_intensity(intensity, |_, channel, value| luts[channel][value as usize])
+    }
+}
@@ -0,0 +1,746 @@
+use std::{collections::VecDeque, fmt};
+
+use super::{
+    adaptive::{sliding_extremum, window_extremum},
+    Image,
+};
+
+/// Neighbourhood morphology operations probe, centered on the pixel. Cells
+/// falling outside the image are ignored.
+#[derive(Clone, PartialEq, Debug)]
+pub enum StructuringElement {
+    /// `2 * radius + 1` pixels wide.
+    Square(u32),
+    /// Horizontal and vertical radius.
+    Rectangle(u32, u32),
+    Disk(u32),
+    /// Horizontal and vertical bars `2 * radius + 1` pixels long.
+    Cross(u32),
+    /// Non-flat disk whose height drops off like a sphere's towards the rim,
+    /// one gray level per pixel. Rolling it under the image, an opening,
+    /// estimates the background. Binary operations only see its disk. In
+    /// grayscale ones, unlike the flat shapes, it takes time proportional to
+    /// its area per pixel.
+    Ball(u32),
+    /// Grid of odd width and height, row by row. Cells that must be
+    /// foreground are `Some(true)`, cells that must be background
+    /// `Some(false)` and the rest don't matter. Only hit-or-miss looks at
+    /// background cells.
+    Custom {
+        width: usize,
+        cells: Vec<Option<bool>>,
+    },
+}
+
+impl StructuringElement {
+    /// Parametric shapes, the custom element isn't one.
+    pub const ALL: [StructuringElement; 5] = [
+        StructuringElement::Square(1),
+        StructuringElement::Rectangle(3, 1),
+        StructuringElement::Disk(1),
+        StructuringElement::Cross(1),
+        StructuringElement::Ball(15),
+    ];
+
+    /// Custom element from rows separated by `/`, `1` for foreground, `0`
+    /// for background and `.` for either, e.g. `010/111/010`. None if rows
+    /// differ in length, there is an even number of rows or columns or an
+    /// unknown character.
+    pub fn parse(pattern: &str) -> Option<Self> {
+        let rows: Vec<&str> = pattern.trim().split('/').collect();
+        let width = rows[0].len();
+        if width.is_multiple_of(2)
+            || rows.len().is_multiple_of(2)
+            || rows.iter().any(|row| row.len() != width)
+        {
+            return None;
+        }
+
+        let cells = rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .map(|cell| match cell {
+                '1' => Some(Some(true)),
+                '0' => Some(Some(false)),
+                '.' => Some(None),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(StructuringElement::Custom { width, cells })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StructuringElement::Square(_) => "Square",
+            StructuringElement::Rectangle(..) => "Rectangle",
+            StructuringElement::Disk(_) => "Disk",
+            StructuringElement::Cross(_) => "Cross",
+            StructuringElement::Ball(_) => "Ball",
+            StructuringElement::Custom { .. } => "Custom",
+        }
+    }
+
+    /// Names of the parameters `values` refers to, in the same order.
+    pub fn parameters(&self) -> &'static [&'static str] {
+        match self {
+            StructuringElement::Rectangle(..) => &["horizontal radius", "vertical radius"],
+            StructuringElement::Custom { .. } => &[],
+            _ => &["radius"],
+        }
+    }
+
+    pub fn values(&self) -> Vec<u32> {
+        match *self {
+            StructuringElement::Square(radius)
+            | StructuringElement::Disk(radius)
+            | StructuringElement::Cross(radius)
+            | StructuringElement::Ball(radius) => vec![radius],
+            StructuringElement::Rectangle(horizontal, vertical) => vec![horizontal, vertical],
+            StructuringElement::Custom { .. } => vec![],
+        }
+    }
+
+    /// Replaces the parameter at `index`. Out of bounds indices leave the
+    /// element unchanged.
+    pub fn with_value(mut self, index: usize, value: u32) -> Self {
+        match (&mut self, index) {
+            (StructuringElement::Square(radius), 0)
+            | (StructuringElement::Disk(radius), 0)
+            | (StructuringElement::Cross(radius), 0)
+            | (StructuringElement::Ball(radius), 0)
+            | (StructuringElement::Rectangle(radius, _), 0)
+            | (StructuringElement::Rectangle(_, radius), 1) => *radius = value,
+            _ => {}
+        }
+
+        self
+    }
+
+    /// Horizontal and vertical radius of the bounding box.
+    fn radii(&self) -> (usize, usize) {
+        match self {
+            StructuringElement::Rectangle(horizontal, vertical) => {
+                (*horizontal as usize, *vertical as usize)
+            }
+            StructuringElement::Custom { width, cells } => (width / 2, cells.len() / width / 2),
+            shape => {
+                let radius = shape.values()[0] as usize;
+                (radius, radius)
+            }
+        }
+    }
+
+    /// Offsets of the cells equal to `value`, `true` for foreground cells
+    /// and `false` for background ones.
+    pub(super) fn offsets(&self, value: bool) -> Vec<(isize, isize)> {
+        let (rx, ry) = self.radii();
+        let (width, height) = (2 * rx + 1, 2 * ry + 1);
+        let cells = match self {
+            StructuringElement::Custom { cells, .. } => cells.clone(),
+            shape => {
+                let (rx, ry) = (rx as isize, ry as isize);
+                let mut cells = Vec::with_capacity(width * height);
+                for dy in -ry..=ry {
+                    for dx in -rx..=rx {
+                        let inside = match shape {
+                            StructuringElement::Disk(_) | StructuringElement::Ball(_) => {
+                                dx * dx + dy * dy <= rx * rx
+                            }
+                            StructuringElement::Cross(_) => dx == 0 || dy == 0,
+                            _ => true,
+                        };
+                        cells.push(Some(inside).filter(|&inside| inside));
+                    }
+                }
+                cells
+            }
+        };
+
+        (0..cells.len())
+            .filter(|&i| cells[i] == Some(value))
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                (x as isize - rx as isize, y as isize - ry as isize)
+            })
+            .collect()
+    }
+
+    /// Foreground offsets with the height of the element there, 0 at the
+    /// center and below 0 elsewhere for non-flat elements.
+    pub(super) fn heights(&self) -> Vec<((isize, isize), f32)> {
+        let radius = self.radii().0 as f32;
+
+        self.offsets(true)
+            .into_iter()
+            .map(|(dx, dy)| {
+                let height = match self {
+                    StructuringElement::Ball(_) => {
+                        let distance = (dx * dx + dy * dy) as f32;
+                        (radius * radius - distance).max(0.0).sqrt() - radius
+                    }
+                    _ => 0.0,
+                };
+                ((dx, dy), height)
+            })
+            .collect()
+    }
+}
+
+/// Minimum (or maximum) of `values` under a flat predefined shape, None for
+/// the ball and custom elements. Rectangles and crosses are one or two van
+/// Herk/Gil-Werman passes, constant time per pixel. Disks are the union of
+/// their horizontal chords, one pass per chord length, so time proportional
+/// to the radius.
+pub(super) fn flat_extremum(
+    values: &[u8],
+    width: usize,
+    height: usize,
+    element: &StructuringElement,
+    max: bool,
+) -> Option<Vec<u8>> {
+    let pick = |a: u8, b: u8| if max { a.max(b) } else { a.min(b) };
+    // Cells further than the image is wide or tall never land inside it, and
+    // a disk reaching past width + height covers the image from any pixel.
+    let (rx, ry) = element.radii();
+    let (rx, ry) = (rx.min(width), ry.min(height));
+
+    match *element {
+        StructuringElement::Square(_) | StructuringElement::Rectangle(..) => {
+            Some(window_extremum(values, width, height, (rx, ry), max))
+        }
+        StructuringElement::Cross(_) => {
+            let horizontal = window_extremum(values, width, height, (rx, 0), max);
+            let vertical = window_extremum(values, width, height, (0, ry), max);
+
+            Some(
+                horizontal
+                    .into_iter()
+                    .zip(vertical)
+                    .map(|(a, b)| pick(a, b))
+                    .collect(),
+            )
+        }
+        StructuringElement::Disk(radius) => {
+            let radius = (radius as usize).min(width + height) as isize;
+            let mut result = vec![if max { u8::MIN } else { u8::MAX }; values.len()];
+            for dy in 0..=radius.min(height as isize) {
+                // Same half-width as the cells `offsets` puts in the disk.
+                let half_width = ((radius * radius - dy * dy) as f64).sqrt().floor() as usize;
+                let chords: Vec<u8> = values
+                    .chunks(width)
+                    .flat_map(|row| sliding_extremum(row, half_width, max))
+                    .collect();
+
+                let shifts: &[isize] = if dy == 0 { &[0] } else { &[dy, -dy] };
+                for dy in shifts {
+                    for y in 0..height as isize {
+                        let source = y + dy;
+                        if source < 0 || source >= height as isize {
+                            continue;
+                        }
+                        let (row, source) = (y as usize * width, source as usize * width);
+                        for x in 0..width {
+                            result[row + x] = pick(result[row + x], chords[source + x]);
+                        }
+                    }
+                }
+            }
+
+            Some(result)
+        }
+        _ => None,
+    }
+}
+
+impl fmt::Display for StructuringElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StructuringElement::Custom { width, cells } => {
+                let rows: Vec<String> = cells
+                    .chunks(*width)
+                    .map(|row| {
+                        row.iter()
+                            .map(|cell| match cell {
+                                Some(true) => '1',
+                                Some(false) => '0',
+                                None => '.',
+                            })
+                            .collect()
+                    })
+                    .collect();
+                write!(f, "{}", rows.join("/"))
+            }
+            shape => {
+                let (rx, ry) = shape.radii();
+                write!(
+                    f,
+                    "{} {}x{}",
+                    shape.name().to_lowercase(),
+                    2 * rx + 1,
+                    2 * ry + 1
+                )
+            }
+        }
+    }
+}
+
+/// Operations on black and white images, dark pixels being the foreground
+/// as in `BinarizationMetrics`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BinaryMorphology {
+    Erode,
+    Dilate,
+    /// Erosion then dilation, removes specks smaller than the element.
+    Open,
+    /// Dilation then erosion, closes gaps smaller than the element.
+    Close,
+    /// Foreground the opening removes.
+    TopHat,
+    /// Background the closing fills.
+    BlackHat,
+    /// Dilation minus erosion, the outlines of shapes.
+    Gradient,
+    /// Pixels whose neighbourhood matches the foreground and background cells
+    /// of the element.
+    /// Only custom elements have background cells, so with the
+    /// other shapes this is an erosion.
+    HitOrMiss,
+    /// Zhang-Suen thinning down to one pixel wide lines.
+    Thin,
+    /// Lantuéjoul's skeleton: union over k of the k-th erosion minus its
+    /// opening.
+    Skeleton,
+    /// Background not connected to the image border becomes foreground.
+    FillHoles,
+}
+
+impl BinaryMorphology {
+    pub const ALL: [BinaryMorphology; 11] = [
+        BinaryMorphology::Erode,
+        BinaryMorphology::Dilate,
+        BinaryMorphology::Open,
+        BinaryMorphology::Close,
+        BinaryMorphology::TopHat,
+        BinaryMorphology::BlackHat,
+        BinaryMorphology::Gradient,
+        BinaryMorphology::HitOrMiss,
+        BinaryMorphology::Thin,
+        BinaryMorphology::Skeleton,
+        BinaryMorphology::FillHoles,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BinaryMorphology::Erode => "Erode",
+            BinaryMorphology::Dilate => "Dilate",
+            BinaryMorphology::Open => "Open",
+            BinaryMorphology::Close => "Close",
+            BinaryMorphology::TopHat => "Top-hat",
+            BinaryMorphology::BlackHat => "Black-hat",
+            BinaryMorphology::Gradient => "Morphological gradient",
+            BinaryMorphology::HitOrMiss => "Hit-or-miss",
+            BinaryMorphology::Thin => "Thin (Zhang-Suen)",
+            BinaryMorphology::Skeleton => "Skeleton (Lantuéjoul)",
+            BinaryMorphology::FillHoles => "Fill holes",
+        }
+    }
+
+    /// False for operations that ignore the structuring element.
+    pub fn uses_element(&self) -> bool {
+        !matches!(self, BinaryMorphology::Thin | BinaryMorphology::FillHoles)
+    }
+}
+
+/// Binary mask with its dimensions.
+struct Mask<'a> {
+    values: &'a [bool],
+    width: usize,
+    height: usize,
+}
+
+impl Mask<'_> {
+    /// True if `test` holds for every offset around `(x, y)` inside the image.
+    fn all(&self, (x, y): (usize, usize), offsets: &[(isize, isize)], test: bool) -> bool {
+        offsets.iter().all(|(dx, dy)| {
+            let (nx, ny) = (x as isize + dx, y as isize + dy);
+            nx < 0
+                || ny < 0
+                || nx as usize >= self.width
+                || ny as usize >= self.height
+                || self.values[ny as usize * self.width + nx as usize] == test
+        })
+    }
+
+    fn map(&self, f: impl Fn((usize, usize)) -> bool) -> Vec<bool> {
+        (0..self.values.len())
+            .map(|i| f((i % self.width, i / self.width)))
+            .collect()
+    }
+
+    /// Sliding minimum (or maximum) under a flat shape, see `flat_extremum`.
+    /// Binary operations only see the disk of a ball.
+    fn flat_extremum(&self, element: &StructuringElement, max: bool) -> Option<Vec<bool>> {
+        let disk;
+        let element = match *element {
+            StructuringElement::Ball(radius) => {
+                disk = StructuringElement::Disk(radius);
+                &disk
+            }
+            _ => element,
+        };
+        let values: Vec<u8> = self.values.iter().map(|&value| value as u8).collect();
+        let result = flat_extremum(&values, self.width, self.height, element, max)?;
+
+        Some(result.into_iter().map(|value| value > 0).collect())
+    }
+
+    fn erode(&self, element: &StructuringElement) -> Vec<bool> {
+        if let Some(result) = self.flat_extremum(element, false) {
+            return result;
+        }
+        let hits = element.offsets(true);
+
+        self.map(|position| self.all(position, &hits, true))
+    }
+
+    fn dilate(&self, element: &StructuringElement) -> Vec<bool> {
+        if let Some(result) = self.flat_extremum(element, true) {
+            return result;
+        }
+        // Dilation probes the reflected element.
+        let hits: Vec<(isize, isize)> = element
+            .offsets(true)
+            .into_iter()
+            .map(|(dx, dy)| (-dx, -dy))
+            .collect();
+
+        self.map(|position| !self.all(position, &hits, false))
+    }
+
+    fn with<'b>(&self, values: &'b [bool]) -> Mask<'b> {
+        Mask {
+            values,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// Zhang-Suen thinning of a foreground mask down to one pixel wide lines.
+pub(crate) fn skeletonize(mask: &[bool], width: usize, height: usize) -> Vec<bool> {
+    let mut skeleton = mask.to_vec();
+    let at = |skeleton: &[bool], x: usize, y: usize, dx: isize, dy: isize| {
+        let (nx, ny) = (x as isize + dx, y as isize + dy);
+        nx >= 0
+            && ny >= 0
+            && (nx as usize) < width
+            && (ny as usize) < height
+            && skeleton[ny as usize * width + nx as usize]
+    };
+
+    loop {
+        let mut changed = false;
+        for pass in 0..2 {
+            let mut removed = Vec::new();
+            for y in 0..height {
+                for x in 0..width {
+                    if !skeleton[y * width + x] {
+                        continue;
+                    }
+
+                    // Neighbours clockwise from north, P2 to P9.
+                    let p = [
+                        at(&skeleton, x, y, 0, -1),
+                        at(&skeleton, x, y, 1, -1),
+                        at(&skeleton, x, y, 1, 0),
+                        at(&skeleton, x, y, 1, 1),
+                        at(&skeleton, x, y, 0, 1),
+                        at(&skeleton, x, y, -1, 1),
+                        at(&skeleton, x, y, -1, 0),
+                        at(&skeleton, x, y, -1, -1),
+                    ];
+                    let neighbours = p.iter().filter(|&&x| x).count();
+                    let transitions = (0..8).filter(|&i| !p[i] && p[(i + 1) % 8]).count();
+                    let (north, east, south, west) = (p[0], p[2], p[4], p[6]);
+                    let removable = if pass == 0 {
+                        !(east && south && (north || west))
+                    } else {
+                        !(north && west && (east || south))
+                    };
+
+                    if (2..=6).contains(&neighbours) && transitions == 1 && removable {
+                        removed.push(y * width + x);
+                    }
+                }
+            }
+
+            changed |= !removed.is_empty();
+            for i in removed {
+                skeleton[i] = false;
+            }
+        }
+
+        if !changed {
+            return skeleton;
+        }
+    }
+}
+
+fn lantuejoul_skeleton(mask: &Mask, element: &StructuringElement) -> Vec<bool> {
+    let mut skeleton = vec![false; mask.values.len()];
+    let mut eroded = mask.values.to_vec();
+
+    loop {
+        let next = mask.with(&eroded).erode(element);
+        let opened = mask.with(&next).dilate(element);
+        for i in 0..skeleton.len() {
+            skeleton[i] |= eroded[i] && !opened[i];
+        }
+
+        // Cells outside the image are ignored, so shapes touching the border
+        // may stop shrinking before they vanish.
+        if next == eroded || !next.contains(&true) {
+            return skeleton;
+        }
+        eroded = next;
+    }
+}
+
+/// Flood fills the background from the border, 4-connected.
+fn fill_holes(mask: &Mask) -> Vec<bool> {
+    let (width, height) = (mask.width, mask.height);
+    let mut reached = vec![false; mask.values.len()];
+    let mut queue = VecDeque::new();
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let border = x == 0 || y == 0 || x + 1 == width || y + 1 == height;
+            if border && !mask.values[i] {
+                reached[i] = true;
+                queue.push_back((x, y));
+            }
+        }
+    }
+
+    while let Some((x, y)) = queue.pop_front() {
+        let neighbours = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        for (nx, ny) in neighbours {
+            if nx < width && ny < height {
+                let i = ny * width + nx;
+                if !reached[i] && !mask.values[i] {
+                    reached[i] = true;
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+    }
+
+    reached.into_iter().map(|reached| !reached).collect()
+}
+
+impl Image {
+    /// Dark pixels, luminance below 128.
+    pub(crate) fn foreground_mask(&self) -> Vec<bool> {
+        self.data
+            .chunks(4)
+            .map(|pixel| Self::luminance(pixel) < 128)
+            .collect()
+    }
+
+    /// Black and white image of a foreground mask, alpha is kept.
+    fn binary_image(&self, mask: &[bool]) -> Self {
+        let mut data = self.data.clone();
+        for (pixel, &foreground) in data.chunks_exact_mut(4).zip(mask) {
+            pixel[..3].fill(if foreground { 0 } else { 255 });
+        }
+
+        Self {
+            data,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    pub fn binary_morphology(
+        &self,
+        operation: BinaryMorphology,
+        element: &StructuringElement,
+    ) -> Self {
+        let values = self.foreground_mask();
+        let mask = Mask {
+            values: &values,
+            width: self.width as usize,
+            height: self.height as usize,
+        };
+        let difference = |a: &[bool], b: &[bool]| -> Vec<bool> {
+            a.iter().zip(b).map(|(&a, &b)| a && !b).collect()
+        };
+        let open = || mask.with(&mask.erode(element)).dilate(element);
+        let close = || mask.with(&mask.dilate(element)).erode(element);
+
+        let result = match operation {
+            BinaryMorphology::Erode => mask.erode(element),
+            BinaryMorphology::Dilate => mask.dilate(element),
+            BinaryMorphology::Open => open(),
+            BinaryMorphology::Close => close(),
+            BinaryMorphology::TopHat => difference(&values, &open()),
+            BinaryMorphology::BlackHat => difference(&close(), &values),
+            BinaryMorphology::Gradient => difference(&mask.dilate(element), &mask.erode(element)),
+            BinaryMorphology::HitOrMiss => {
+                let (hits, misses) = (element.offsets(true), element.offsets(false));
+                mask.map(|position| {
+                    mask.all(position, &hits, true) && mask.all(position, &misses, false)
+                })
+            }
+            BinaryMorphology::Thin => skeletonize(&values, mask.width, mask.height),
+            BinaryMorphology::Skeleton => lantuejoul_skeleton(&mask, element),
+            BinaryMorphology::FillHoles => fill_holes(&mask),
+        };
+
+        self.binary_image(&result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Image from rows of `#` for black foreground and `.` for white.
+    fn image(rows: &[&str]) -> Image {
+        let data = rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .flat_map(|cell| {
+                let level = if cell == '#' { 0 } else { 255 };
+                [level, level, level, 255]
+            })
+            .collect();
+
+        Image {
+            width: rows[0].len() as u32,
+            height: rows.len() as u32,
+            data,
+        }
+    }
+
+    fn rows(image: &Image) -> Vec<String> {
+        image
+            .foreground_mask()
+            .chunks(image.width as usize)
+            .map(|row| {
+                row.iter()
+                    .map(|&cell| if cell { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The same cells as a custom element, which takes the generic path.
+    fn custom(element: &StructuringElement) -> StructuringElement {
+        let (rx, ry) = element.radii();
+        let width = 2 * rx + 1;
+        let mut cells = vec![None; width * (2 * ry + 1)];
+        for (dx, dy) in element.offsets(true) {
+            cells[(dy + ry as isize) as usize * width + (dx + rx as isize) as usize] = Some(true);
+        }
+
+        StructuringElement::Custom { width, cells }
+    }
+
+    #[test]
+    fn erosion_and_dilation_of_simple_shapes() {
+        let square = image(&[".......", ".#####.", ".#####.", ".#####.", "......."]);
+        assert_eq!(
+            rows(
+                &square.binary_morphology(BinaryMorphology::Erode, &StructuringElement::Square(1))
+            ),
+            [".......", ".......", "..###..", ".......", "......."]
+        );
+
+        let dot = image(&[".....", ".....", "..#..", ".....", "....."]);
+        assert_eq!(
+            rows(&dot.binary_morphology(BinaryMorphology::Dilate, &StructuringElement::Cross(1))),
+            [".....", "..#..", ".###.", "..#..", "....."]
+        );
+        assert_eq!(
+            rows(&dot.binary_morphology(BinaryMorphology::Dilate, &StructuringElement::Disk(2))),
+            ["..#..", ".###.", "#####", ".###.", "..#.."]
+        );
+    }
+
+    #[test]
+    fn predefined_shapes_match_their_custom_cells() {
+        let (width, height) = (21, 16);
+        let rows: Vec<String> = (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| {
+                        if (x * 7 + y * 13 + x * y) % 5 < 2 {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+        let image = image(&rows);
+
+        let elements = [
+            StructuringElement::Square(1),
+            StructuringElement::Rectangle(3, 1),
+            StructuringElement::Rectangle(0, 2),
+            StructuringElement::Disk(1),
+            StructuringElement::Disk(3),
+            StructuringElement::Cross(2),
+            StructuringElement::Ball(4),
+        ];
+        let operations = [
+            BinaryMorphology::Erode,
+            BinaryMorphology::Dilate,
+            BinaryMorphology::Open,
+            BinaryMorphology::Close,
+            BinaryMorphology::Gradient,
+        ];
+        for element in &elements {
+            for operation in operations {
+                assert!(
+                    image.binary_morphology(operation, element)
+                        == image.binary_morphology(operation, &custom(element)),
+                    "{} with {}",
+                    operation.name(),
+                    element
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fill_holes_leaves_background_reaching_the_border() {
+        let shapes = image(&["#####....", "#...#.#.#", "#####.#.#", ".......##"]);
+        assert_eq!(
+            rows(
+                &shapes
+                    .binary_morphology(BinaryMorphology::FillHoles, &StructuringElement::Square(1))
+            ),
+            ["#####....", "#####.#.#", "#####.#.#", ".......##"]
+        );
+    }
+
+    #[test]
+    fn hit_or_miss_finds_isolated_pixels() {
+        let element = StructuringElement::parse("000/010/000").unwrap();
+        let pixels = image(&["......", ".#..##", "....##", "......"]);
+        assert_eq!(
+            rows(&pixels.binary_morphology(BinaryMorphology::HitOrMiss, &element)),
+            ["......", ".#....", "......", "......"]
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use std::fmt;
+
+use super::color::{rgb_to_hsv, rgb_to_lab};
+use super::{ColorComponent, Image, Lut};
+
+/// How `Image::threshold_with` decides whether a pixel is in range. Every
+/// mode except `PerChannel` renders a black and white result.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ThresholdMode {
+    /// White if any of red, green or blue is in range.
+    Any { low: u8, high: u8 },
+    /// White if red, green and blue all are in range.
+    All { low: u8, high: u8 },
+    /// White if the luminance `get_grayscale_histogram` counts is in range.
+    Luminance { low: u8, high: u8 },
+    /// Each channel is set to 255 if it is in its own range and to 0
+    /// otherwise, so the result keeps up to eight colors.
+    PerChannel {
+        red: (u8, u8),
+        green: (u8, u8),
+        blue: (u8, u8),
+    },
+    /// White if hue, saturation and value all are in range. A hue band with
+    /// low above high wraps around 0°, e.g. (330, 30) selects reds.
+    Hsv {
+        hue: (f32, f32),
+        saturation: (f32, f32),
+        value: (f32, f32),
+    },
+    /// White if L*, a* and b* all are in range.
+    Lab {
+        lightness: (f32, f32),
+        a: (f32, f32),
+        b: (f32, f32),
+    },
+}
+
+impl ThresholdMode {
+    pub const ALL: [ThresholdMode; 6] = [
+        ThresholdMode::Any { low: 0, high: 255 },
+        ThresholdMode::All { low: 0, high: 255 },
+        ThresholdMode::Luminance { low: 0, high: 255 },
+        ThresholdMode::PerChannel {
+            red: (0, 255),
+            green: (0, 255),
+            blue: (0, 255),
+        },
+        ThresholdMode::Hsv {
+            hue: (0.0, 360.0),
+            saturation: (0.0, 1.0),
+            value: (0.0, 1.0),
+        },
+        ThresholdMode::Lab {
+            lightness: (0.0, 100.0),
+            a: (-128.0, 127.0),
+            b: (-128.0, 127.0),
+        },
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThresholdMode::Any { .. } => "Any channel",
+            ThresholdMode::All { .. } => "All channels",
+            ThresholdMode::Luminance { .. } => "Luminance",
+            ThresholdMode::PerChannel { .. } => "Per channel",
+            ThresholdMode::Hsv { .. } => "HSV",
+            ThresholdMode::Lab { .. } => "Lab",
+        }
+    }
+
+    /// Names of the components `ranges` refers to, in the same order.
+    pub fn components(&self) -> &'static [&'static str] {
+        match self {
+            ThresholdMode::Any { .. } | ThresholdMode::All { .. } => &["RGB"],
+            ThresholdMode::Luminance { .. } => &["Luminance"],
+            ThresholdMode::PerChannel { .. } => &["Red", "Green", "Blue"],
+            ThresholdMode::Hsv { .. } => &["Hue", "Saturation", "Value"],
+            ThresholdMode::Lab { .. } => &["L*", "a*", "b*"],
+        }
+    }
+
+    /// Smallest and largest value of each component.
+    pub fn limits(&self) -> &'static [(f32, f32)] {
+        match self {
+            ThresholdMode::Any { .. }
+            | ThresholdMode::All { .. }
+            | ThresholdMode::Luminance { .. } => &[(0.0, 255.0)],
+            ThresholdMode::PerChannel { .. } => &[(0.0, 255.0); 3],
+            ThresholdMode::Hsv { .. } => &[(0.0, 360.0), (0.0, 1.0), (0.0, 1.0)],
+            ThresholdMode::Lab { .. } => &[(0.0, 100.0), (-128.0, 127.0), (-128.0, 127.0)],
+        }
+    }
+
+    /// `PerChannel` as a lookup table, the other modes combine channels.
+    pub fn lut(&self) -> Option<Lut> {
+        match *self {
+            ThresholdMode::PerChannel { red, green, blue } => {
+                Some(Lut::per_channel([red, green, blue].map(|(low, high)| {
+                    let mut table = [0; 256];
+                    if low <= high {
+                        table[low as usize..=high as usize].fill(255);
+                    }
+                    table
+                })))
+            }
+            _ => None,
+        }
+    }
+
+    /// True if the range of the component at `index` may have its low end
+    /// above its high end to wrap around.
+    pub fn wraps(&self, index: usize) -> bool {
+        matches!(self, ThresholdMode::Hsv { .. }) && index == 0
+    }
+
+    pub fn ranges(&self) -> Vec<(f32, f32)> {
+        let float = |(low, high): (u8, u8)| (low as f32, high as f32);
+        match *self {
+            ThresholdMode::Any { low, high }
+            | ThresholdMode::All { low, high }
+            | ThresholdMode::Luminance { low, high } => vec![float((low, high))],
+            ThresholdMode::PerChannel { red, green, blue } => {
+                vec![float(red), float(green), float(blue)]
+            }
+            ThresholdMode::Hsv {
+                hue,
+                saturation,
+                value,
+            } => vec![hue, saturation, value],
+            ThresholdMode::Lab { lightness, a, b } => vec![lightness, a, b],
+        }
+    }
+
+    /// Replaces the range of the component at `index`, clamped to its
+    /// limits. Out of bounds indices leave the mode unchanged.
+    pub fn with_range(mut self, index: usize, (low, high): (f32, f32)) -> Self {
+        let (min, max) = match self.limits().get(index) {
+            Some(limits) => *limits,
+            None => return self,
+        };
+        let range = (low.clamp(min, max), high.clamp(min, max));
+        let byte = |(low, high): (f32, f32)| (low.round() as u8, high.round() as u8);
+
+        match &mut self {
+            ThresholdMode::Any { low, high }
+            | ThresholdMode::All { low, high }
+            | ThresholdMode::Luminance { low, high } => (*low, *high) = byte(range),
+            ThresholdMode::PerChannel { red, green, blue } => {
+                *[red, green, blue][index] = byte(range);
+            }
+            ThresholdMode::Hsv {
+                hue,
+                saturation,
+                value,
+            } => *[hue, saturation, value][index] = range,
+            ThresholdMode::Lab { lightness, a, b } => *[lightness, a, b][index] = range,
+        }
+
+        self
+    }
+}
+
+impl fmt::Display for ThresholdMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())?;
+        for (component, (low, high)) in self.components().iter().zip(self.ranges()) {
+            write!(f, " {} [{}, {}]", component, low, high)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn in_range(value: f32, (low, high): (f32, f32)) -> bool {
+    low <= value && value <= high
+}
+
+fn in_hue_range(hue: f32, (low, high): (f32, f32)) -> bool {
+    if low <= high {
+        in_range(hue, (low, high))
+    } else {
+        hue >= low || hue <= high
+    }
+}
+
+impl Image {
+    pub fn threshold_with(&self, mode: ThresholdMode) -> Self {
+        if let Some(lut) = mode.lut() {
+            return self.apply_lut(&lut);
+        }
+
+        let ranges = mode.ranges();
+        let mut data = self.data.clone();
+        for (i, pixel) in self.data.chunks(4).enumerate() {
+            let (r, g, b) = (pixel[0], pixel[1], pixel[2]);
+            let values = match mode {
+                ThresholdMode::Any { .. } => {
+                    [[r, g, b].iter().any(|&v| in_range(v as f32, ranges[0])); 3]
+                }
+                ThresholdMode::All { .. } => {
+                    [[r, g, b].iter().all(|&v| in_range(v as f32, ranges[0])); 3]
+                }
+                ThresholdMode::Luminance { .. } => {
+                    [in_range(Self::luminance(pixel) as f32, ranges[0]); 3]
+                }
+                ThresholdMode::PerChannel { .. } => unreachable!("applied as a lookup table"),
+                ThresholdMode::Hsv { .. } => {
+                    let (h, s, v) = rgb_to_hsv(r, g, b);
+                    let selected = in_hue_range(h, ranges[0])
+                        && in_range(s, ranges[1])
+                        && in_range(v, ranges[2]);
+                    [selected; 3]
+                }
+                ThresholdMode::Lab { .. } => {
+                    let (l, a, b) = rgb_to_lab(r, g, b);
+                    let selected =
+                        in_range(l, ranges[0]) && in_range(a, ranges[1]) && in_range(b, ranges[2]);
+                    [selected; 3]
+                }
+            };
+
+            for (component, selected) in [
+                ColorComponent::Red,
+                ColorComponent::Green,
+                ColorComponent::Blue,
+            ]
+            .iter()
+            .zip(values)
+            {
+                data[(i * 4) + *component as usize] = if selected { 255 } else { 0 };
+            }
+        }
+
+        Self {
+            data,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
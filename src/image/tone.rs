@@ -0,0 +1,209 @@
+use std::fmt;
+
+use super::{Image, Intensity};
+
+/// Manual levels adjustment: the input range is stretched onto the output
+/// range after a gamma correction of the midtones.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Levels {
+    /// Black and white points of the input, levels outside are clipped.
+    pub input: (u8, u8),
+    /// Above 1 brightens the midtones, below 1 darkens them.
+    pub gamma: f32,
+    /// Levels black and white are mapped to. White below black inverts.
+    pub output: (u8, u8),
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Self {
+            input: (0, 255),
+            gamma: 1.0,
+            output: (0, 255),
+        }
+    }
+}
+
+impl Levels {
+    pub fn lut(&self) -> [u8; 256] {
+        let (black, white) = (self.input.0 as f32, self.input.1 as f32);
+        let (low, high) = (self.output.0 as f32, self.output.1 as f32);
+        let range = (white - black).max(1.0);
+        let exponent = 1.0 / self.gamma.max(0.01);
+
+        let mut lut = [0; 256];
+        for (i, entry) in lut.iter_mut().enumerate() {
+            let t = ((i as f32 - black) / range).clamp(0.0, 1.0).powf(exponent);
+            *entry = (low + t * (high - low)).round().clamp(0.0, 255.0) as u8;
+        }
+
+        lut
+    }
+}
+
+impl fmt::Display for Levels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "input [{}, {}], gamma {}, output [{}, {}]",
+            self.input.0, self.input.1, self.gamma, self.output.0, self.output.1
+        )
+    }
+}
+
+/// Tone curve through control points, interpolated by a monotone cubic
+/// spline so it never overshoots between them. Levels left of the first or
+/// right of the last point keep that point's output.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Curve {
+    /// Sorted by input level, at most one point per input level.
+    points: Vec<(u8, u8)>,
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Self {
+            points: vec![(0, 0), (255, 255)],
+        }
+    }
+}
+
+impl Curve {
+    /// Curve through `points`. Of points sharing an input level the last
+    /// one is kept, no points gives the identity.
+    pub fn new(points: Vec<(u8, u8)>) -> Self {
+        let mut curve = Self { points: Vec::new() };
+        for point in points {
+            curve.insert(point);
+        }
+        if curve.points.is_empty() {
+            return Self::default();
+        }
+
+        curve
+    }
+
+    pub fn points(&self) -> &[(u8, u8)] {
+        &self.points
+    }
+
+    /// Adds a control point, replacing any at the same input level, and
+    /// returns its index.
+    pub fn insert(&mut self, (x, y): (u8, u8)) -> usize {
+        match self.points.binary_search_by_key(&x, |point| point.0) {
+            Ok(index) => {
+                self.points[index].1 = y;
+                index
+            }
+            Err(index) => {
+                self.points.insert(index, (x, y));
+                index
+            }
+        }
+    }
+
+    /// Moves the point at `index`, keeping its input level strictly between
+    /// those of its neighbours so the order doesn't change.
+    pub fn move_point(&mut self, index: usize, (x, y): (u8, u8)) {
+        if index >= self.points.len() {
+            return;
+        }
+        let min = match index {
+            0 => 0,
+            _ => self.points[index - 1].0.saturating_add(1),
+        };
+        let max = match self.points.get(index + 1) {
+            Some(next) => next.0.saturating_sub(1),
+            None => 255,
+        };
+
+        self.points[index] = (x.clamp(min, max.max(min)), y);
+    }
+
+    /// Removes the point at `index` unless only two are left.
+    pub fn remove(&mut self, index: usize) {
+        if self.points.len() > 2 && index < self.points.len() {
+            self.points.remove(index);
+        }
+    }
+
+    pub fn lut(&self) -> [u8; 256] {
+        let xs: Vec<f32> = self.points.iter().map(|point| point.0 as f32).collect();
+        let ys: Vec<f32> = self.points.iter().map(|point| point.1 as f32).collect();
+        let n = xs.len();
+        if n == 1 {
+            return [self.points[0].1; 256];
+        }
+
+        let widths: Vec<f32> = xs.windows(2).map(|x| x[1] - x[0]).collect();
+        let slopes: Vec<f32> = (0..n - 1)
+            .map(|k| (ys[k + 1] - ys[k]) / widths[k])
+            .collect();
+
+        // Fritsch-Carlson tangents in the Brodlie form: zero at local
+        // extrema, otherwise a weighted harmonic mean of the neighbouring
+        // slopes.
+        let mut tangents = vec![0.0; n];
+        tangents[0] = slopes[0];
+        tangents[n - 1] = slopes[n - 2];
+        for k in 1..n - 1 {
+            let (before, after) = (slopes[k - 1], slopes[k]);
+            if before * after > 0.0 {
+                let w1 = 2.0 * widths[k] + widths[k - 1];
+                let w2 = widths[k] + 2.0 * widths[k - 1];
+                tangents[k] = (w1 + w2) / (w1 / before + w2 / after);
+            }
+        }
+
+        let mut lut = [0; 256];
+        let mut k = 0;
+        for (i, entry) in lut.iter_mut().enumerate() {
+            let x = i as f32;
+            let y = if x <= xs[0] {
+                ys[0]
+            } else if x >= xs[n - 1] {
+                ys[n - 1]
+            } else {
+                while xs[k + 1] < x {
+                    k += 1;
+                }
+                let h = widths[k];
+                let t = (x - xs[k]) / h;
+                let (t2, t3) = (t * t, t * t * t);
+                (2.0 * t3 - 3.0 * t2 + 1.0) * ys[k]
+                    + (t3 - 2.0 * t2 + t) * h * tangents[k]
+                    + (-2.0 * t3 + 3.0 * t2) * ys[k + 1]
+                    + (t3 - t2) * h * tangents[k + 1]
+            };
+            *entry = y.round().clamp(0.0, 255.0) as u8;
+        }
+
+        lut
+    }
+}
+
+impl fmt::Display for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let points: Vec<String> = self
+            .points
+            .iter()
+            .map(|(x, y)| format!("({}, {})", x, y))
+            .collect();
+
+        write!(f, "{}", points.join(" "))
+    }
+}
+
+impl Image {
+    pub fn apply_levels(&self, levels: &Levels, intensity: Intensity) -> Self {
+        let lut = levels.lut();
+
+        self.map_intensity(intensity, |_, _, value| lut[value as usize])
+    }
+
+    pub fn apply_curve(&self, curve: &Curve, intensity: Intensity) -> Self {
+        let lut = curve.lut();
+
+        self.map_intensity(intensity, |_, _, value| lut[value as usize])
+    }
+}
@@ -1,36 +1,686 @@
+use binhis::{
+    history::History,
+    image::{
+        AdaptiveMethod, Adjustment, BinaryMorphology, GrayMorphology, HistogramTarget, Image,
+        Intensity, StructuringElement, ThresholdMode, MAX_DIMENSION,
+    },
+    pipeline::{Operation, Pipeline},
+    report::Report,
+    threshold::ThresholdMethod,
+};
+use gloo_events::EventListener;
+use image::ImageOutputFormat;
 use wasm_bindgen::{Clamped, JsCast};
-use web_sys::{CanvasRenderingContext2d, Event, HtmlCanvasElement, HtmlInputElement, ImageData};
+use web_sys::{
+    CanvasRenderingContext2d, Event, HtmlCanvasElement, HtmlInputElement, HtmlSelectElement,
+    ImageData, InputEvent, KeyboardEvent,
+};
 use yew::{html, Component, NodeRef, Properties};
 
-use crate::image::Image;
+use crate::{
+    channel_component::ChannelComponent,
+    criterion_component::CriterionComponent,
+    download::download,
+    histogram_component::{HistogramComponent, Histograms},
+    metrics_component::MetricsComponent,
+    montage_component::MontageComponent,
+    shared_image::SharedImage,
+    tone_component::ToneComponent,
+    upload::upload,
+};
+
+const HISTORY_SNAPSHOT_BUDGET: usize = 64 * 1024 * 1024;
+const INVALID_PATTERN: &str = "Rows of 1, 0 and . must have the same odd length, in an odd number";
+
+#[derive(Clone, Copy, PartialEq)]
+enum ExportFormat {
+    Png,
+    Bmp,
+    Jpeg,
+    Gif,
+}
+
+impl ExportFormat {
+    const ALL: [ExportFormat; 4] = [
+        ExportFormat::Png,
+        ExportFormat::Bmp,
+        ExportFormat::Jpeg,
+        ExportFormat::Gif,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Png => "PNG",
+            ExportFormat::Bmp => "BMP",
+            ExportFormat::Jpeg => "JPEG",
+            ExportFormat::Gif => "GIF",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Png => "png",
+            ExportFormat::Bmp => "bmp",
+            ExportFormat::Jpeg => "jpg",
+            ExportFormat::Gif => "gif",
+        }
+    }
+
+    fn mime_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Png => "image/png",
+            ExportFormat::Bmp => "image/bmp",
+            ExportFormat::Jpeg => "image/jpeg",
+            ExportFormat::Gif => "image/gif",
+        }
+    }
+
+    fn output_format(&self, jpeg_quality: u8) -> ImageOutputFormat {
+        match self {
+            ExportFormat::Png => ImageOutputFormat::Png,
+            ExportFormat::Bmp => ImageOutputFormat::Bmp,
+            ExportFormat::Jpeg => ImageOutputFormat::Jpeg(jpeg_quality),
+            ExportFormat::Gif => ImageOutputFormat::Gif,
+        }
+    }
+}
 
 #[derive(Properties, PartialEq)]
 pub struct Props {
-    pub image_data: Vec<u8>,
+    pub image: Image,
 }
 
 pub enum Msg {
     StretchHistogram,
     EqualizeHistogram,
+    NormalizeIntensityChanged(Event),
+    ApplyClippedStretch,
+    StretchClipLowChanged(Event),
+    StretchClipHighChanged(Event),
+    StretchLinkToggled,
+    ApplyClahe,
+    ClaheColumnsChanged(Event),
+    ClaheRowsChanged(Event),
+    ClipLimitChanged(Event),
+    ApplyHistogramMatch,
+    MatchTargetChanged(Event),
+    MatchParameterChanged(usize, Event),
+    ReferenceUpload(Event),
+    ReferenceLoaded(Vec<u8>),
+    ReferenceReadFailed,
+    ToneApplied(Operation),
+    ApplyAdjustment,
+    AdjustmentChanged(Event),
+    AdjustmentParameterChanged(usize, InputEvent),
+    PreviewCancelled,
     ApplyThreshold,
-    ApplyMeanIterativeSelectionThreshold,
-    ApplyPercentBlackSelectionThreshold,
-    ApplyEntropySelectionThreshold,
-    ApplyMinimumErrorThreshold,
-    ApplyFuzzyMinimumErrorThreshold,
-    TresholdLowChanged(Event),
-    TresholdHighChanged(Event),
-    PercentBlackChanged(Event)
+    ApplyAutoThreshold,
+    ThresholdMethodChanged(Event),
+    MontageToggled,
+    MethodPromoted(ThresholdMethod),
+    ApplyMultiOtsuThreshold,
+    OtsuLevelsChanged(Event),
+    ApplyAdaptiveThreshold,
+    AdaptiveMethodChanged(Event),
+    AdaptiveWindowChanged(Event),
+    AdaptiveKChanged(Event),
+    AdaptiveRChanged(Event),
+    AdaptiveContrastLimitChanged(Event),
+    ApplyMorphology,
+    MorphologyChanged(Event),
+    ApplyGrayMorphology,
+    GrayMorphologyChanged(Event),
+    ElementChanged(MorphologyKind, Event),
+    ElementParameterChanged(MorphologyKind, usize, Event),
+    ElementPatternChanged(MorphologyKind, Event),
+    ThresholdModeChanged(Event),
+    TresholdLowChanged(usize, Event),
+    TresholdHighChanged(usize, Event),
+    PercentBlackChanged(Event),
+    StepMovedUp(usize),
+    StepMovedDown(usize),
+    StepToggled(usize),
+    StepRemoved(usize),
+    PipelineCleared,
+    Undo,
+    Redo,
+    Export,
+    ExportFormatChanged(Event),
+    JpegQualityChanged(Event),
+    ExportBinaryToggled,
+    ExportReport(ReportFormat),
+}
+
+/// Which morphology row a structuring element belongs to.
+#[derive(Clone, Copy)]
+pub enum MorphologyKind {
+    Binary,
+    Gray,
+}
+
+/// Structuring element picked in a morphology row.
+struct ElementInput {
+    element: StructuringElement,
+    /// Text of the custom element, kept while it doesn't parse.
+    pattern: String,
+    error: Option<String>,
+}
+
+impl ElementInput {
+    fn new(element: StructuringElement) -> Self {
+        Self {
+            element,
+            pattern: "010/111/010".to_string(),
+            error: None,
+        }
+    }
+
+    /// Selects `StructuringElement::ALL[index]`, or the custom element for
+    /// the option after the last.
+    fn select(&mut self, index: usize) {
+        match StructuringElement::ALL.get(index) {
+            Some(shape) => {
+                self.element = shape.clone();
+                self.error = None;
+            }
+            None => self.set_pattern(self.pattern.clone()),
+        }
+    }
+
+    fn set_pattern(&mut self, pattern: String) {
+        match StructuringElement::parse(&pattern) {
+            Some(element) => {
+                self.element = element;
+                self.error = None;
+            }
+            None => {
+                // Keep the custom option selected while the pattern is fixed.
+                if !matches!(self.element, StructuringElement::Custom { .. }) {
+                    self.element = StructuringElement::Custom {
+                        width: 1,
+                        cells: vec![Some(true)],
+                    };
+                }
+                self.error = Some(INVALID_PATTERN.to_string());
+            }
+        }
+        self.pattern = pattern;
+    }
+}
+
+pub enum ReportFormat {
+    HistogramsCsv,
+    ThresholdsCsv,
+    Json,
 }
 
 pub struct ImageComponent {
     image: Image,
-    image_to_display: Image,
+    image_to_display: SharedImage,
+    pipeline: Pipeline,
+    history: History,
+    normalize_intensity: Intensity,
+    stretch_clip: (f32, f32),
+    stretch_linked: bool,
+    stretch_ranges: Vec<(u8, u8)>,
+    clahe_grid: (u32, u32),
+    clip_limit: f32,
+    match_target: HistogramTarget,
+    reference: Option<HistogramTarget>,
+    reference_error: Option<String>,
+    adjustment: Adjustment,
+    /// Output of `adjustment` shown in place of the pipeline's until it's
+    /// applied or cancelled.
+    preview: Option<Image>,
+    keydown_listener: Option<EventListener>,
     canvas_ref: NodeRef,
     canvas_ctx: Option<CanvasRenderingContext2d>,
-    treshold_low: u8,
-    treshold_high: u8,
+    threshold_mode: ThresholdMode,
     black_percent: f32,
+    threshold_method: ThresholdMethod,
+    last_selection: Option<(ThresholdMethod, u8)>,
+    show_montage: bool,
+    comparison: Vec<(&'static str, u8, u8)>,
+    otsu_levels: usize,
+    adaptive_method: AdaptiveMethod,
+    adaptive_window: u32,
+    morphology: BinaryMorphology,
+    binary_element: ElementInput,
+    gray_morphology: GrayMorphology,
+    gray_element: ElementInput,
+    export_format: ExportFormat,
+    jpeg_quality: u8,
+    export_binary: bool,
+    export_error: Option<String>,
+    is_binary: bool,
+    original_histograms: Histograms,
+    processed_histograms: Histograms,
+}
+
+impl ImageComponent {
+    fn display(&mut self, image: Image) {
+        self.processed_histograms = Histograms::of(&image);
+        self.is_binary = image.is_binary();
+        self.image_to_display = SharedImage::new(image);
+        self.preview = None;
+        self.compare_thresholds();
+        self.update_stretch_ranges();
+    }
+
+    fn adjustment_operation(&self) -> Operation {
+        Operation::Adjust {
+            adjustment: self.adjustment,
+            intensity: self.normalize_intensity,
+        }
+    }
+
+    fn update_preview(&mut self) {
+        self.preview = Some(self.adjustment_operation().apply(&self.image_to_display));
+    }
+
+    fn update_stretch_ranges(&mut self) {
+        self.stretch_ranges = self.image_to_display.clipped_ranges(
+            self.stretch_clip,
+            self.normalize_intensity,
+            self.stretch_linked,
+        );
+    }
+
+    fn select(&mut self, method: ThresholdMethod) {
+        let selection = method.compute(&self.processed_histograms.grayscale);
+        self.last_selection = Some((method, selection.threshold));
+        if self.threshold_mode.components().len() == 1 {
+            self.threshold_mode = self
+                .threshold_mode
+                .with_range(0, (selection.threshold as f32, 255.0));
+        }
+        self.push(Operation::AutoThreshold(method));
+    }
+
+    fn compare_thresholds(&mut self) {
+        self.comparison = ThresholdMethod::ALL
+            .iter()
+            .map(|method| method.with_black_percent(self.black_percent))
+            .map(|method| {
+                (
+                    method.name(),
+                    method
+                        .compute(&self.original_histograms.grayscale)
+                        .threshold,
+                    method
+                        .compute(&self.processed_histograms.grayscale)
+                        .threshold,
+                )
+            })
+            .collect();
+    }
+
+    // The prop checks html! emits for a component taking a Callback trip this
+    // lint.
+    #[allow(clippy::unnecessary_operation)]
+    fn view_montage(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        if !self.show_montage {
+            return html! {};
+        }
+
+        html! {
+            <MontageComponent
+                image={self.image_to_display.clone()}
+                black_percent={self.black_percent}
+                on_promote={ctx.link().callback(Msg::MethodPromoted)}
+            />
+        }
+    }
+
+    #[allow(clippy::unnecessary_operation)]
+    fn view_tone(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        html! {
+            <ToneComponent
+                image={self.image_to_display.clone()}
+                intensity={self.normalize_intensity}
+                on_apply={ctx.link().callback(Msg::ToneApplied)}
+            />
+        }
+    }
+
+    fn view_comparison(&self) -> yew::Html {
+        html! {
+            <details>
+                <summary>{"Compare treshold methods"}</summary>
+                <table>
+                    <tr>
+                        <th>{"Method"}</th>
+                        <th>{"Original"}</th>
+                        <th>{"Current output"}</th>
+                    </tr>
+                    { for self.comparison.iter().map(|(name, original, processed)| html! {
+                        <tr>
+                            <td>{name}</td>
+                            <td>{original}</td>
+                            <td>{processed}</td>
+                        </tr>
+                    }) }
+                </table>
+            </details>
+        }
+    }
+
+    fn export(&self) -> Result<(), String> {
+        let format = self.export_format;
+        let bytes = if format == ExportFormat::Png && self.export_binary && self.is_binary {
+            self.image_to_display.encode_binary_png()
+        } else {
+            self.image_to_display
+                .encode(format.output_format(self.jpeg_quality))
+        }
+        .map_err(|error| error.to_string())?;
+
+        download(
+            &bytes,
+            format.mime_type(),
+            &format!("binhis.{}", format.extension()),
+        )
+        .map_err(|_| "Couldn't start the download.".to_string())
+    }
+
+    fn export_report(&self, format: ReportFormat) -> Result<(), String> {
+        let report = Report::new(&self.image, &self.image_to_display, self.black_percent);
+        let (contents, mime_type, filename) = match format {
+            ReportFormat::HistogramsCsv => (report.histograms_csv(), "text/csv", "histograms.csv"),
+            ReportFormat::ThresholdsCsv => (report.thresholds_csv(), "text/csv", "thresholds.csv"),
+            ReportFormat::Json => (report.to_json(), "application/json", "report.json"),
+        };
+
+        download(contents.as_bytes(), mime_type, filename)
+            .map_err(|_| "Couldn't start the download.".to_string())
+    }
+
+    fn view_export(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let link = ctx.link();
+
+        html! {
+            <div>
+                <select onchange={link.callback(|event: Event| Msg::ExportFormatChanged(event))}>
+                    { for ExportFormat::ALL.iter().enumerate().map(|(i, format)| html! {
+                        <option value={i.to_string()} selected={*format == self.export_format}>
+                            {format.name()}
+                        </option>
+                    }) }
+                </select>
+                if self.export_format == ExportFormat::Jpeg {
+                    <label>{"Quality "}
+                        <input type="number" min="1" max="100" step="1"
+                            value={self.jpeg_quality.to_string()}
+                            onchange={link.callback(|event: Event| Msg::JpegQualityChanged(event))} />
+                    </label>
+                }
+                if self.export_format == ExportFormat::Png {
+                    <label title="Only available for black and white output">
+                        <input type="checkbox" checked={self.export_binary}
+                            disabled={!self.is_binary}
+                            onchange={link.callback(|_| Msg::ExportBinaryToggled)} />
+                        {"1-bit"}
+                    </label>
+                }
+                <button onclick={link.callback(|_| Msg::Export)}>{"Export"}</button>
+                <button onclick={link.callback(|_| Msg::ExportReport(ReportFormat::HistogramsCsv))}>
+                    {"Histograms (CSV)"}
+                </button>
+                <button onclick={link.callback(|_| Msg::ExportReport(ReportFormat::ThresholdsCsv))}>
+                    {"Thresholds (CSV)"}
+                </button>
+                <button onclick={link.callback(|_| Msg::ExportReport(ReportFormat::Json))}>
+                    {"Histograms and thresholds (JSON)"}
+                </button>
+                if let Some(error) = &self.export_error {
+                    <span style="color: red;">{error}</span>
+                }
+            </div>
+        }
+    }
+
+    fn push(&mut self, operation: Operation) {
+        // The current output is the result of all previous steps, so only the
+        // new one needs to run.
+        let image = operation.apply(&self.image_to_display);
+        self.record();
+        self.pipeline.push(operation);
+        self.display(image);
+    }
+
+    fn record(&mut self) {
+        self.history
+            .record(self.pipeline.clone(), (*self.image_to_display).clone());
+    }
+
+    fn restore(&mut self, (pipeline, snapshot): (Pipeline, Option<Image>)) {
+        self.pipeline = pipeline;
+        match snapshot {
+            Some(image) => self.display(image),
+            None => self.rerun(),
+        }
+    }
+
+    fn rerun(&mut self) {
+        self.display(self.pipeline.run(&self.image));
+    }
+
+    fn view_pipeline(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let link = ctx.link();
+        let steps = self.pipeline.get_steps();
+
+        html! {
+            <div>
+                <span>{"Pipeline"}</span>
+                <button disabled={!self.history.can_undo()} title="Ctrl+Z"
+                    onclick={link.callback(|_| Msg::Undo)}>{"Undo"}</button>
+                <button disabled={!self.history.can_redo()} title="Ctrl+Shift+Z"
+                    onclick={link.callback(|_| Msg::Redo)}>{"Redo"}</button>
+                <button disabled={self.pipeline.is_empty()}
+                    onclick={link.callback(|_| Msg::PipelineCleared)}>{"Clear"}</button>
+                if steps.is_empty() {
+                    <div>{"No operations applied, showing the original image."}</div>
+                }
+                <ol>
+                    { for steps.iter().enumerate().map(|(i, step)| html! {
+                        <li>
+                            <input type="checkbox" checked={step.enabled}
+                                onchange={link.callback(move |_| Msg::StepToggled(i))} />
+                            <span style={if step.enabled { "" } else { "text-decoration: line-through;" }}>
+                                {step.operation.to_string()}
+                            </span>
+                            <button disabled={i == 0}
+                                onclick={link.callback(move |_| Msg::StepMovedUp(i))}>{"↑"}</button>
+                            <button disabled={i + 1 == steps.len()}
+                                onclick={link.callback(move |_| Msg::StepMovedDown(i))}>{"↓"}</button>
+                            <button onclick={link.callback(move |_| Msg::StepRemoved(i))}>{"✕"}</button>
+                        </li>
+                    }) }
+                </ol>
+            </div>
+        }
+    }
+
+    fn view_threshold_ranges(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let link = ctx.link();
+        let mode = &self.threshold_mode;
+
+        html! {
+            { for mode.ranges().into_iter().enumerate().map(|(i, (low, high))| {
+                let (min, max) = mode.limits()[i];
+                let step = if max - min <= 1.0 { "0.01" } else { "1" };
+                html! {
+                    <label>{format!("{} ", mode.components()[i])}
+                        <input type="number" min={min.to_string()} max={max.to_string()} {step}
+                            value={low.to_string()}
+                            onchange={link.callback(move |event: Event| Msg::TresholdLowChanged(i, event))} />
+                        <input type="number" min={min.to_string()} max={max.to_string()} {step}
+                            value={high.to_string()}
+                            onchange={link.callback(move |event: Event| Msg::TresholdHighChanged(i, event))} />
+                    </label>
+                }
+            }) }
+        }
+    }
+
+    fn view_stretch_ranges(&self) -> String {
+        let ranges: Vec<String> = self
+            .stretch_ranges
+            .iter()
+            .map(|(min, max)| format!("[{}, {}]", min, max))
+            .collect();
+
+        match (self.normalize_intensity, self.stretch_linked) {
+            (Intensity::PerChannel, false) => format!(
+                "R {} G {} B {}",
+                ranges[0], ranges[1], ranges[2]
+            ),
+            _ => ranges[0].clone(),
+        }
+    }
+
+    fn view_adjustment_parameters(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let link = ctx.link();
+        let adjustment = &self.adjustment;
+
+        html! {
+            { for adjustment.values().into_iter().enumerate().map(|(i, value)| {
+                let (min, max) = adjustment.limits()[i];
+                let step = if max - min <= 10.0 { "0.05" } else { "1" };
+                html! {
+                    <label>{format!(" {} ", adjustment.parameters()[i])}
+                        <input type="range" min={min.to_string()} max={max.to_string()} {step}
+                            value={value.to_string()}
+                            oninput={link.callback(move |event: InputEvent| Msg::AdjustmentParameterChanged(i, event))} />
+                        <span>{value.to_string()}</span>
+                    </label>
+                }
+            }) }
+        }
+    }
+
+    fn element_input(&mut self, kind: MorphologyKind) -> &mut ElementInput {
+        match kind {
+            MorphologyKind::Binary => &mut self.binary_element,
+            MorphologyKind::Gray => &mut self.gray_element,
+        }
+    }
+
+    fn view_structuring_element(
+        &self,
+        ctx: &yew::Context<Self>,
+        kind: MorphologyKind,
+    ) -> yew::Html {
+        let link = ctx.link();
+        let input = match kind {
+            MorphologyKind::Binary => &self.binary_element,
+            MorphologyKind::Gray => &self.gray_element,
+        };
+        let element = &input.element;
+
+        html! {
+            <>
+                <label>{" element "}
+                    <select onchange={link.callback(move |event: Event| Msg::ElementChanged(kind, event))}>
+                        { for StructuringElement::ALL.iter().enumerate().map(|(i, shape)| html! {
+                            <option value={i.to_string()} selected={shape.name() == element.name()}>
+                                {shape.name()}
+                            </option>
+                        }) }
+                        <option value={StructuringElement::ALL.len().to_string()}
+                            selected={matches!(element, StructuringElement::Custom { .. })}>
+                            {"Custom"}
+                        </option>
+                    </select>
+                </label>
+                { for element.values().into_iter().enumerate().map(|(i, value)| html! {
+                    <label>{format!(" {} ", element.parameters()[i])}
+                        <input type="number" min="0" max="100" step="1"
+                            value={value.to_string()}
+                            onchange={link.callback(move |event: Event| Msg::ElementParameterChanged(kind, i, event))} />
+                    </label>
+                }) }
+                if let StructuringElement::Custom { .. } = element {
+                    <label title="Rows separated by /, 1 for foreground, 0 for background, . for either">
+                        {" pattern "}
+                        <input type="text" value={input.pattern.clone()}
+                            onchange={link.callback(move |event: Event| Msg::ElementPatternChanged(kind, event))} />
+                    </label>
+                    if let Some(error) = &input.error {
+                        <span style="color: red;">{error}</span>
+                    }
+                }
+            </>
+        }
+    }
+
+    fn view_match_target(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let link = ctx.link();
+        let target = &self.match_target;
+
+        if let HistogramTarget::Reference(_) = target {
+            return html! {
+                <>
+                    <input type="file"
+                        onchange={link.callback(|event: Event| Msg::ReferenceUpload(event))} />
+                    if let Some(error) = &self.reference_error {
+                        <span style="color: red;">{error}</span>
+                    }
+                </>
+            };
+        }
+
+        html! {
+            { for target.values().into_iter().enumerate().map(|(i, value)| {
+                let (min, max) = target.limits()[i];
+                let step = if max - min <= 1.0 { "0.001" } else { "1" };
+                html! {
+                    <label>{format!(" {} ", target.parameters()[i])}
+                        <input type="number" min={min.to_string()} max={max.to_string()} {step}
+                            value={value.to_string()}
+                            onchange={link.callback(move |event: Event| Msg::MatchParameterChanged(i, event))} />
+                    </label>
+                }
+            }) }
+        }
+    }
+
+    fn view_adaptive_parameters(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let link = ctx.link();
+        let k_input = |k: f32| {
+            html! {
+                <label>{"k "}
+                    <input type="number" step="0.05" value={k.to_string()}
+                        onchange={link.callback(|event: Event| Msg::AdaptiveKChanged(event))} />
+                </label>
+            }
+        };
+        let r_input = |r: f32, step: &'static str| {
+            html! {
+                <label>{"R "}
+                    <input type="number" min={step} {step} value={r.to_string()}
+                        onchange={link.callback(|event: Event| Msg::AdaptiveRChanged(event))} />
+                </label>
+            }
+        };
+
+        match self.adaptive_method {
+            AdaptiveMethod::Niblack { k } | AdaptiveMethod::Wolf { k } => k_input(k),
+            AdaptiveMethod::Sauvola { k, r } => html! {
+                <>{k_input(k)}{r_input(r, "1")}</>
+            },
+            AdaptiveMethod::Phansalkar { k, r, .. } => html! {
+                <>{k_input(k)}{r_input(r, "0.05")}</>
+            },
+            AdaptiveMethod::Bernsen { contrast_limit } => html! {
+                <label>{"Contrast limit "}
+                    <input type="number" min="0" max="255" step="1"
+                        value={contrast_limit.to_string()}
+                        onchange={link.callback(|event: Event| Msg::AdaptiveContrastLimitChanged(event))} />
+                </label>
+            },
+        }
+    }
 }
 
 impl Component for ImageComponent {
@@ -38,16 +688,53 @@ impl Component for ImageComponent {
     type Properties = Props;
 
     fn create(ctx: &yew::Context<Self>) -> Self {
-        let image = Image::new_with_data(ctx.props().image_data.clone());
-        Self {
+        let image = ctx.props().image.clone();
+        let histograms = Histograms::of(&image);
+        let is_binary = image.is_binary();
+        let mut component = Self {
             image: image.clone(),
-            image_to_display: image,
+            image_to_display: SharedImage::new(image),
+            pipeline: Pipeline::new(),
+            history: History::new(HISTORY_SNAPSHOT_BUDGET),
+            normalize_intensity: Intensity::PerChannel,
+            stretch_clip: (0.005, 0.005),
+            stretch_linked: false,
+            stretch_ranges: Vec::new(),
+            clahe_grid: (8, 8),
+            clip_limit: 2.0,
+            match_target: HistogramTarget::ALL[0].clone(),
+            reference: None,
+            reference_error: None,
+            adjustment: Adjustment::ALL[0],
+            preview: None,
+            keydown_listener: None,
             canvas_ref: NodeRef::default(),
             canvas_ctx: None,
-            treshold_low: 0,
-            treshold_high: 255,
+            threshold_mode: ThresholdMode::ALL[0],
             black_percent: 0.0,
-        }
+            threshold_method: ThresholdMethod::Otsu,
+            last_selection: None,
+            show_montage: false,
+            comparison: Vec::new(),
+            otsu_levels: 2,
+            adaptive_method: AdaptiveMethod::ALL[0],
+            adaptive_window: 15,
+            morphology: BinaryMorphology::ALL[0],
+            binary_element: ElementInput::new(StructuringElement::ALL[0].clone()),
+            gray_morphology: GrayMorphology::Open,
+            gray_element: ElementInput::new(StructuringElement::ALL[0].clone()),
+            export_format: ExportFormat::Png,
+            jpeg_quality: 90,
+            export_binary: false,
+            export_error: None,
+            is_binary,
+            original_histograms: histograms.clone(),
+            processed_histograms: histograms,
+        };
+        component.compare_thresholds();
+        component.update_stretch_ranges();
+
+        component
     }
 
     fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
@@ -58,119 +745,721 @@ impl Component for ImageComponent {
                 <div>
                     <button onclick={link.callback(|_| Msg::StretchHistogram )}>{"Normalize (stretch histogram)"}</button>
                     <button onclick={link.callback(|_| Msg::EqualizeHistogram )}>{"Normalize (equalize histogram)"}</button>
+                    <label>{" on "}
+                        <select onchange={link.callback(|event: Event| Msg::NormalizeIntensityChanged(event))}>
+                            { for Intensity::ALL.iter().enumerate().map(|(i, intensity)| html! {
+                                <option value={i.to_string()} selected={*intensity == self.normalize_intensity}>
+                                    {intensity.name()}
+                                </option>
+                            }) }
+                        </select>
+                    </label>
+                </div>
+                <div>
+                    <button onclick={link.callback(|_| Msg::ApplyClippedStretch )}>{"Normalize (clipped stretch)"}</button>
+                    <label>{" dark "}
+                        <input type="range" min="0" max="0.1" step="0.001"
+                            value={self.stretch_clip.0.to_string()}
+                            onchange={link.callback(|event: Event| Msg::StretchClipLowChanged(event))} />
+                        <span>{format!("{:.1}%", self.stretch_clip.0 * 100.0)}</span>
+                    </label>
+                    <label>{" bright "}
+                        <input type="range" min="0" max="0.1" step="0.001"
+                            value={self.stretch_clip.1.to_string()}
+                            onchange={link.callback(|event: Event| Msg::StretchClipHighChanged(event))} />
+                        <span>{format!("{:.1}%", self.stretch_clip.1 * 100.0)}</span>
+                    </label>
+                    if self.normalize_intensity == Intensity::PerChannel {
+                        <label>
+                            <input type="checkbox" checked={self.stretch_linked}
+                                onchange={link.callback(|_| Msg::StretchLinkToggled)} />
+                            {"Link channels"}
+                        </label>
+                    }
+                    <span>{format!(" input range {}", self.view_stretch_ranges())}</span>
+                </div>
+                <div>
+                    <button onclick={link.callback(|_| Msg::ApplyClahe )}>{"Normalize (CLAHE)"}</button>
+                    <label>{" tiles "}
+                        <input type="number" min="1" max="64" step="1"
+                            value={self.clahe_grid.0.to_string()}
+                            onchange={link.callback(|event: Event| Msg::ClaheColumnsChanged(event))} />
+                    </label>
+                    <label>{" x "}
+                        <input type="number" min="1" max="64" step="1"
+                            value={self.clahe_grid.1.to_string()}
+                            onchange={link.callback(|event: Event| Msg::ClaheRowsChanged(event))} />
+                    </label>
+                    <label>{" clip limit "}
+                        <input type="number" min="0" max="64" step="0.5"
+                            value={self.clip_limit.to_string()}
+                            onchange={link.callback(|event: Event| Msg::ClipLimitChanged(event))} />
+                    </label>
+                </div>
+                <div>
+                    <label title="Open with a large ball or rectangle to estimate the background">
+                        {"Grayscale morphology "}
+                        <select onchange={link.callback(|event: Event| Msg::GrayMorphologyChanged(event))}>
+                            { for GrayMorphology::ALL.iter().enumerate().map(|(i, operation)| html! {
+                                <option value={i.to_string()} selected={*operation == self.gray_morphology}>
+                                    {operation.name()}
+                                </option>
+                            }) }
+                        </select>
+                    </label>
+                    { self.view_structuring_element(ctx, MorphologyKind::Gray) }
+                    <button onclick={link.callback(|_| Msg::ApplyGrayMorphology)}
+                        disabled={self.gray_element.error.is_some()}>
+                        {"Apply grayscale morphology"}
+                    </button>
+                </div>
+                <div>
+                    <button onclick={link.callback(|_| Msg::ApplyHistogramMatch )}
+                        disabled={self.match_target == HistogramTarget::Reference(Vec::new())}>
+                        {"Normalize (match histogram)"}
+                    </button>
+                    <label>{" to "}
+                        <select onchange={link.callback(|event: Event| Msg::MatchTargetChanged(event))}>
+                            <option value="0" selected={matches!(self.match_target, HistogramTarget::Reference(_))}>
+                                {"Reference image"}
+                            </option>
+                            { for HistogramTarget::ALL.iter().enumerate().map(|(i, target)| html! {
+                                <option value={(i + 1).to_string()}
+                                    selected={target.name() == self.match_target.name()}>
+                                    {target.name()}
+                                </option>
+                            }) }
+                        </select>
+                    </label>
+                    { self.view_match_target(ctx) }
+                </div>
+                <div>
+                    <select onchange={link.callback(|event: Event| Msg::AdjustmentChanged(event))}>
+                        { for Adjustment::ALL.iter().enumerate().map(|(i, adjustment)| html! {
+                            <option value={i.to_string()}
+                                selected={adjustment.name() == self.adjustment.name()}>
+                                {adjustment.name()}
+                            </option>
+                        }) }
+                    </select>
+                    { self.view_adjustment_parameters(ctx) }
+                    <button onclick={link.callback(|_| Msg::ApplyAdjustment )}>{"Apply adjustment"}</button>
+                    if self.preview.is_some() {
+                        <button onclick={link.callback(|_| Msg::PreviewCancelled )}>{"Cancel preview"}</button>
+                    }
                 </div>
+                { self.view_tone(ctx) }
                 <div>
-                    <input type="number" min="0" max={self.treshold_high.to_string()} step="1"
-                        value={self.treshold_low.to_string()}
-                        onchange={link.callback(|event: Event| Msg::TresholdLowChanged(event))} />
-                    <input type="number" min={self.treshold_low.to_string()} max="255" step="1"
-                        value={self.treshold_high.to_string()}
-                        onchange={link.callback(|event: Event| Msg::TresholdHighChanged(event))} />
+                    <select onchange={link.callback(|event: Event| Msg::ThresholdModeChanged(event))}>
+                        { for ThresholdMode::ALL.iter().enumerate().map(|(i, mode)| html! {
+                            <option value={i.to_string()}
+                                selected={mode.name() == self.threshold_mode.name()}>
+                                {mode.name()}
+                            </option>
+                        }) }
+                    </select>
+                    { self.view_threshold_ranges(ctx) }
                     <button onclick={link.callback(|_| Msg::ApplyThreshold )}>{"Apply treshold"}</button>
                 </div>
                 <div>
-                    <input type="range" min="0" max="1" step="0.01"
-                        value={self.black_percent.to_string()}
-                        onchange={link.callback(|event: Event| Msg::PercentBlackChanged(event))} />
-                    <span>{format!("{:.2}%", self.black_percent * 100.0)}</span>
-                    <button onclick={link.callback(|_| Msg::ApplyPercentBlackSelectionThreshold )}>{"Apply treshold (Percent Black Selection)"}</button>
+                    <select onchange={link.callback(|event: Event| Msg::ThresholdMethodChanged(event))}>
+                        { for ThresholdMethod::ALL.iter().enumerate().map(|(i, method)| html! {
+                            <option value={i.to_string()}
+                                selected={method.name() == self.threshold_method.name()}>
+                                {method.name()}
+                            </option>
+                        }) }
+                    </select>
+                    if let ThresholdMethod::PercentBlack(_) = self.threshold_method {
+                        <input type="range" min="0" max="1" step="0.01"
+                            value={self.black_percent.to_string()}
+                            onchange={link.callback(|event: Event| Msg::PercentBlackChanged(event))} />
+                        <span>{format!("{:.2}%", self.black_percent * 100.0)}</span>
+                    }
+                    <button onclick={link.callback(|_| Msg::ApplyAutoThreshold )}>{"Apply treshold"}</button>
+                    if let Some((method, threshold)) = &self.last_selection {
+                        <span>{format!("{}: threshold = {}", method.name(), threshold)}</span>
+                    }
+                    <label>
+                        <input type="checkbox" checked={self.show_montage}
+                            onchange={link.callback(|_| Msg::MontageToggled)} />
+                        {"Try all methods"}
+                    </label>
                 </div>
+                { self.view_montage(ctx) }
+                { self.view_comparison() }
+                <ChannelComponent image={self.image_to_display.clone()} />
+                <MetricsComponent
+                    image={self.image_to_display.clone()}
+                    black_percent={self.black_percent}
+                />
                 <div>
-                    <button onclick={link.callback(|_| Msg::ApplyMeanIterativeSelectionThreshold )}>{"Apply treshold (Mean Iterative Selection)"}</button>
-                    <button onclick={link.callback(|_| Msg::ApplyEntropySelectionThreshold )}>{"Apply treshold (Entropy Selection)"}</button>
-                    <button onclick={link.callback(|_| Msg::ApplyMinimumErrorThreshold )}>{"Apply treshold (Minimum Error)"}</button>
-                    <button onclick={link.callback(|_| Msg::ApplyFuzzyMinimumErrorThreshold )}>{"Apply treshold (Fuzzy Minimum Error)"}</button>
+                    <input type="number" min="1" max="8" step="1"
+                        value={self.otsu_levels.to_string()}
+                        onchange={link.callback(|event: Event| Msg::OtsuLevelsChanged(event))} />
+                    <button onclick={link.callback(|_| Msg::ApplyMultiOtsuThreshold )}>{"Apply multi-level treshold (Otsu)"}</button>
                 </div>
                 <div>
+                    <select onchange={link.callback(|event: Event| Msg::AdaptiveMethodChanged(event))}>
+                        { for AdaptiveMethod::ALL.iter().enumerate().map(|(i, method)| html! {
+                            <option value={i.to_string()}
+                                selected={method.name() == self.adaptive_method.name()}>
+                                {method.name()}
+                            </option>
+                        }) }
+                    </select>
+                    <label>{"Window "}
+                        <input type="number" min="3" max={MAX_DIMENSION.to_string()} step="2"
+                            value={self.adaptive_window.to_string()}
+                            onchange={link.callback(|event: Event| Msg::AdaptiveWindowChanged(event))} />
+                    </label>
+                    { self.view_adaptive_parameters(ctx) }
+                    <button onclick={link.callback(|_| Msg::ApplyAdaptiveThreshold )}>{"Apply adaptive treshold"}</button>
+                </div>
+                <div>
+                    <label title="Dark pixels are the foreground">{"Morphology "}
+                        <select onchange={link.callback(|event: Event| Msg::MorphologyChanged(event))}>
+                            { for BinaryMorphology::ALL.iter().enumerate().map(|(i, operation)| html! {
+                                <option value={i.to_string()} selected={*operation == self.morphology}>
+                                    {operation.name()}
+                                </option>
+                            }) }
+                        </select>
+                    </label>
+                    if self.morphology.uses_element() {
+                        { self.view_structuring_element(ctx, MorphologyKind::Binary) }
+                    }
+                    <button onclick={link.callback(|_| Msg::ApplyMorphology)}
+                        disabled={self.morphology.uses_element() && self.binary_element.error.is_some()}>
+                        {"Apply morphology"}
+                    </button>
+                </div>
+                { self.view_export(ctx) }
+                <div style="display: flex; align-items: flex-start; gap: 16px;">
                     <canvas ref={self.canvas_ref.clone()}
                         width={self.image.get_width().to_string()}
                         height={self.image.get_height().to_string()}
                     />
+                    <div>
+                        <HistogramComponent
+                            original={self.original_histograms.clone()}
+                            processed={self.processed_histograms.clone()}
+                        />
+                        <CriterionComponent
+                            histogram={self.processed_histograms.grayscale}
+                            black_percent={self.black_percent}
+                        />
+                    </div>
+                    { self.view_pipeline(ctx) }
                 </div>
             </>
         }
     }
 
-    fn update(&mut self, _ctx: &yew::Context<Self>, msg: Self::Message) -> bool {
+    fn update(&mut self, ctx: &yew::Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::StretchHistogram => {
-                self.image_to_display = self.image.get_stretched_image();
+                self.push(Operation::Stretch(self.normalize_intensity));
 
                 true
             }
             Msg::EqualizeHistogram => {
-                self.image_to_display = self.image.get_equalized_image();
+                self.push(Operation::Equalize(self.normalize_intensity));
+
+                true
+            }
+            Msg::NormalizeIntensityChanged(event) => {
+                let select: HtmlSelectElement = event.target().unwrap().dyn_into().unwrap();
+                let index = select.selected_index().max(0) as usize;
+                self.normalize_intensity = Intensity::ALL[index.min(Intensity::ALL.len() - 1)];
+                self.update_stretch_ranges();
+                if self.preview.is_some() {
+                    self.update_preview();
+                }
+
+                true
+            }
+            Msg::ApplyClippedStretch => {
+                self.push(Operation::StretchClipped {
+                    clip: self.stretch_clip,
+                    intensity: self.normalize_intensity,
+                    linked: self.stretch_linked,
+                });
+
+                true
+            }
+            Msg::StretchClipLowChanged(event) => {
+                let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+                self.stretch_clip.0 = (input.value_as_number() as f32).clamp(0.0, 0.5);
+                self.update_stretch_ranges();
+
+                true
+            }
+            Msg::StretchClipHighChanged(event) => {
+                let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+                self.stretch_clip.1 = (input.value_as_number() as f32).clamp(0.0, 0.5);
+                self.update_stretch_ranges();
+
+                true
+            }
+            Msg::StretchLinkToggled => {
+                self.stretch_linked = !self.stretch_linked;
+                self.update_stretch_ranges();
+
+                true
+            }
+            Msg::ApplyClahe => {
+                self.push(Operation::Clahe {
+                    grid: self.clahe_grid,
+                    clip_limit: self.clip_limit,
+                    intensity: self.normalize_intensity,
+                });
+
+                true
+            }
+            Msg::ClaheColumnsChanged(event) => {
+                let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+                self.clahe_grid.0 = (input.value_as_number() as u32).clamp(1, 64);
+
+                true
+            }
+            Msg::ClaheRowsChanged(event) => {
+                let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+                self.clahe_grid.1 = (input.value_as_number() as u32).clamp(1, 64);
+
+                true
+            }
+            Msg::ClipLimitChanged(event) => {
+                let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+                let value = input.value_as_number() as f32;
+                if value.is_finite() {
+                    self.clip_limit = value.max(0.0);
+                }
+
+                true
+            }
+            Msg::ApplyHistogramMatch => {
+                self.push(Operation::MatchHistogram {
+                    target: self.match_target.clone(),
+                    intensity: self.normalize_intensity,
+                });
+
+                true
+            }
+            Msg::MatchTargetChanged(event) => {
+                let select: HtmlSelectElement = event.target().unwrap().dyn_into().unwrap();
+                // The first option is the reference image, which has no
+                // entry in `HistogramTarget::ALL`.
+                self.match_target = match select.selected_index().max(0) as usize {
+                    0 => self
+                        .reference
+                        .clone()
+                        .unwrap_or(HistogramTarget::Reference(Vec::new())),
+                    index => HistogramTarget::ALL[(index - 1).min(HistogramTarget::ALL.len() - 1)]
+                        .clone(),
+                };
+
+                true
+            }
+            Msg::MatchParameterChanged(index, event) => {
+                let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+                let value = input.value_as_number() as f32;
+                if value.is_finite() {
+                    self.match_target = self.match_target.clone().with_value(index, value);
+                }
+
+                true
+            }
+            Msg::ReferenceUpload(event) => {
+                let file_cb = ctx
+                    .link()
+                    .callback(|value: Vec<u8>| Msg::ReferenceLoaded(value));
+                let error_cb = ctx.link().callback(|_| Msg::ReferenceReadFailed);
+                upload(&event, file_cb, error_cb);
+                self.reference_error = None;
+
+                true
+            }
+            Msg::ReferenceLoaded(data) => {
+                match Image::new_with_data(data) {
+                    Ok(image) => {
+                        let reference = HistogramTarget::reference(&image);
+                        self.match_target = reference.clone();
+                        self.reference = Some(reference);
+                    }
+                    Err(error) => self.reference_error = Some(error.to_string()),
+                }
+
+                true
+            }
+            Msg::ReferenceReadFailed => {
+                self.reference_error = Some("Couldn't read file.".to_string());
+
+                true
+            }
+            Msg::ToneApplied(operation) => {
+                self.push(operation);
+
+                true
+            }
+            Msg::ApplyAdjustment => {
+                self.push(self.adjustment_operation());
+
+                true
+            }
+            Msg::AdjustmentChanged(event) => {
+                let select: HtmlSelectElement = event.target().unwrap().dyn_into().unwrap();
+                let index = select.selected_index().max(0) as usize;
+                self.adjustment = Adjustment::ALL[index.min(Adjustment::ALL.len() - 1)];
+                self.update_preview();
+
+                true
+            }
+            Msg::AdjustmentParameterChanged(index, event) => {
+                let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+                let value = input.value_as_number() as f32;
+                if value.is_finite() {
+                    self.adjustment = self.adjustment.with_value(index, value);
+                    self.update_preview();
+                }
+
+                true
+            }
+            Msg::PreviewCancelled => {
+                self.preview = None;
 
                 true
             }
             Msg::ApplyThreshold => {
-                self.image_to_display = self
-                    .image
-                    .threshold((self.treshold_low, self.treshold_high));
+                self.push(Operation::Threshold(self.threshold_mode));
 
                 true
             }
-            Msg::TresholdLowChanged(event) => {
+            Msg::ThresholdModeChanged(event) => {
+                let select: HtmlSelectElement = event.target().unwrap().dyn_into().unwrap();
+                let index = select.selected_index().max(0) as usize;
+                let mode = ThresholdMode::ALL[index.min(ThresholdMode::ALL.len() - 1)];
+                // Single band modes share their range.
+                self.threshold_mode = match self.threshold_mode.ranges().as_slice() {
+                    [range] if mode.components().len() == 1 => mode.with_range(0, *range),
+                    _ => mode,
+                };
+
+                true
+            }
+            Msg::TresholdLowChanged(index, event) => {
                 let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
-                self.treshold_low = input.value_as_number() as u8;
-                if self.treshold_low > self.treshold_high {
-                    self.treshold_high = self.treshold_low;
+                let low = input.value_as_number() as f32;
+                let (_, mut high) = self.threshold_mode.ranges()[index];
+                if low > high && !self.threshold_mode.wraps(index) {
+                    high = low;
                 }
+                self.threshold_mode = self.threshold_mode.with_range(index, (low, high));
 
                 true
             }
-            Msg::TresholdHighChanged(event) => {
+            Msg::TresholdHighChanged(index, event) => {
                 let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
-                self.treshold_high = input.value_as_number() as u8;
-                if self.treshold_high < self.treshold_low {
-                    self.treshold_high = self.treshold_low;
+                let (low, _) = self.threshold_mode.ranges()[index];
+                let mut high = input.value_as_number() as f32;
+                if high < low && !self.threshold_mode.wraps(index) {
+                    high = low;
                 }
+                self.threshold_mode = self.threshold_mode.with_range(index, (low, high));
 
                 true
             }
-            Msg::ApplyMeanIterativeSelectionThreshold => {
-                self.image_to_display = self.image.mean_iterative_selection();
+            Msg::ApplyAutoThreshold => {
+                self.select(self.threshold_method.with_black_percent(self.black_percent));
 
                 true
             }
-            Msg::ApplyPercentBlackSelectionThreshold => {
-                self.image_to_display = self.image.percent_black_selection(self.black_percent);
+            Msg::ThresholdMethodChanged(event) => {
+                let select: HtmlSelectElement = event.target().unwrap().dyn_into().unwrap();
+                let index = select.selected_index().max(0) as usize;
+                self.threshold_method =
+                    ThresholdMethod::ALL[index.min(ThresholdMethod::ALL.len() - 1)];
 
                 true
-            },
+            }
+            Msg::MontageToggled => {
+                self.show_montage = !self.show_montage;
+
+                true
+            }
+            Msg::MethodPromoted(method) => {
+                self.threshold_method = method;
+                self.select(method);
+
+                true
+            }
             Msg::PercentBlackChanged(event) => {
                 let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
                 self.black_percent = input.value_as_number() as f32;
+                self.compare_thresholds();
 
                 true
-            },
-            Msg::ApplyEntropySelectionThreshold => {
-                self.image_to_display = self.image.entropy_selection();
+            }
+            Msg::ApplyMultiOtsuThreshold => {
+                self.push(Operation::MultiOtsu(self.otsu_levels));
 
                 true
-            },
-            Msg::ApplyMinimumErrorThreshold => {
-                self.image_to_display = self.image.minimum_error_selection();
+            }
+            Msg::OtsuLevelsChanged(event) => {
+                let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+                self.otsu_levels = (input.value_as_number() as usize).clamp(1, 8);
 
                 true
-            },
-            Msg::ApplyFuzzyMinimumErrorThreshold => {
-                self.image_to_display = self.image.fuzzy_minimum_error_selection();
+            }
+            Msg::ApplyAdaptiveThreshold => {
+                self.push(Operation::Adaptive {
+                    window: self.adaptive_window,
+                    method: self.adaptive_method,
+                });
 
                 true
-            },
+            }
+            Msg::AdaptiveMethodChanged(event) => {
+                let select: HtmlSelectElement = event.target().unwrap().dyn_into().unwrap();
+                let index = select.selected_index().max(0) as usize;
+                self.adaptive_method =
+                    AdaptiveMethod::ALL[index.min(AdaptiveMethod::ALL.len() - 1)];
+
+                true
+            }
+            Msg::ApplyMorphology => {
+                self.push(Operation::BinaryMorphology {
+                    operation: self.morphology,
+                    element: self.binary_element.element.clone(),
+                });
+
+                true
+            }
+            Msg::MorphologyChanged(event) => {
+                let select: HtmlSelectElement = event.target().unwrap().dyn_into().unwrap();
+                let index = select.selected_index().max(0) as usize;
+                self.morphology = BinaryMorphology::ALL[index.min(BinaryMorphology::ALL.len() - 1)];
+
+                true
+            }
+            Msg::ApplyGrayMorphology => {
+                self.push(Operation::GrayMorphology {
+                    operation: self.gray_morphology,
+                    element: self.gray_element.element.clone(),
+                    intensity: self.normalize_intensity,
+                });
+
+                true
+            }
+            Msg::GrayMorphologyChanged(event) => {
+                let select: HtmlSelectElement = event.target().unwrap().dyn_into().unwrap();
+                let index = select.selected_index().max(0) as usize;
+                self.gray_morphology =
+                    GrayMorphology::ALL[index.min(GrayMorphology::ALL.len() - 1)];
+
+                true
+            }
+            Msg::ElementChanged(kind, event) => {
+                let select: HtmlSelectElement = event.target().unwrap().dyn_into().unwrap();
+                self.element_input(kind)
+                    .select(select.selected_index().max(0) as usize);
+
+                true
+            }
+            Msg::ElementParameterChanged(kind, index, event) => {
+                let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+                let value = input.value_as_number();
+                if value.is_finite() {
+                    let element_input = self.element_input(kind);
+                    element_input.element = element_input
+                        .element
+                        .clone()
+                        .with_value(index, value.clamp(0.0, 100.0) as u32);
+                }
+
+                true
+            }
+            Msg::ElementPatternChanged(kind, event) => {
+                let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+                self.element_input(kind).set_pattern(input.value());
+
+                true
+            }
+            Msg::AdaptiveWindowChanged(event) => {
+                let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+                // Keep the window odd so it stays centered on the pixel. No
+                // image is wider than MAX_DIMENSION, larger windows add nothing.
+                let window = input.value_as_number() as u32;
+                self.adaptive_window = window.clamp(3, MAX_DIMENSION) | 1;
+
+                true
+            }
+            Msg::AdaptiveKChanged(event) => {
+                let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+                let value = input.value_as_number() as f32;
+                match &mut self.adaptive_method {
+                    AdaptiveMethod::Niblack { k }
+                    | AdaptiveMethod::Sauvola { k, .. }
+                    | AdaptiveMethod::Wolf { k }
+                    | AdaptiveMethod::Phansalkar { k, .. } => *k = value,
+                    AdaptiveMethod::Bernsen { .. } => {}
+                }
+
+                true
+            }
+            Msg::AdaptiveRChanged(event) => {
+                let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+                let value = input.value_as_number() as f32;
+                // R divides the deviation, keep it at least one step above 0.
+                if value.is_finite() {
+                    match &mut self.adaptive_method {
+                        AdaptiveMethod::Sauvola { r, .. } => *r = value.max(1.0),
+                        AdaptiveMethod::Phansalkar { r, .. } => *r = value.max(0.05),
+                        _ => {}
+                    }
+                }
+
+                true
+            }
+            Msg::AdaptiveContrastLimitChanged(event) => {
+                let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+                if let AdaptiveMethod::Bernsen { contrast_limit } = &mut self.adaptive_method {
+                    *contrast_limit = input.value_as_number() as u8;
+                }
+
+                true
+            }
+            Msg::StepMovedUp(index) => {
+                self.record();
+                self.pipeline.move_up(index);
+                self.rerun();
+
+                true
+            }
+            Msg::StepMovedDown(index) => {
+                self.record();
+                self.pipeline.move_down(index);
+                self.rerun();
+
+                true
+            }
+            Msg::StepToggled(index) => {
+                self.record();
+                self.pipeline.toggle(index);
+                self.rerun();
+
+                true
+            }
+            Msg::StepRemoved(index) => {
+                self.record();
+                self.pipeline.remove(index);
+                self.rerun();
+
+                true
+            }
+            Msg::PipelineCleared => {
+                self.record();
+                self.pipeline.clear();
+                self.rerun();
+
+                true
+            }
+            Msg::Export => {
+                self.export_error = self.export().err();
+
+                true
+            }
+            Msg::ExportReport(format) => {
+                self.export_error = self.export_report(format).err();
+
+                true
+            }
+            Msg::ExportFormatChanged(event) => {
+                let select: HtmlSelectElement = event.target().unwrap().dyn_into().unwrap();
+                let index = select.selected_index().max(0) as usize;
+                self.export_format = ExportFormat::ALL[index.min(ExportFormat::ALL.len() - 1)];
+
+                true
+            }
+            Msg::JpegQualityChanged(event) => {
+                let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+                self.jpeg_quality = (input.value_as_number() as u8).clamp(1, 100);
+
+                true
+            }
+            Msg::ExportBinaryToggled => {
+                self.export_binary = !self.export_binary;
+
+                true
+            }
+            Msg::Undo => {
+                let state = self
+                    .history
+                    .undo(self.pipeline.clone(), (*self.image_to_display).clone());
+                match state {
+                    Some(state) => {
+                        self.restore(state);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            Msg::Redo => {
+                let state = self
+                    .history
+                    .redo(self.pipeline.clone(), (*self.image_to_display).clone());
+                match state {
+                    Some(state) => {
+                        self.restore(state);
+                        true
+                    }
+                    None => false,
+                }
+            }
         }
     }
 
     fn changed(&mut self, ctx: &yew::Context<Self>) -> bool {
-        self.image = Image::new_with_data(ctx.props().image_data.clone());
-        self.image_to_display = self.image.clone();
+        self.image = ctx.props().image.clone();
+        // Snapshots and pipelines of the previous upload don't apply to this
+        // image, which may not even have the same dimensions.
+        self.history.clear();
+        // None of the steps have been applied to the new image.
+        self.pipeline.clear();
+        self.original_histograms = Histograms::of(&self.image);
+        self.display(self.image.clone());
 
         true
     }
 
-    fn rendered(&mut self, _ctx: &yew::Context<Self>, first_render: bool) {
+    fn rendered(&mut self, ctx: &yew::Context<Self>, first_render: bool) {
         if first_render {
+            let undo_cb = ctx.link().callback(|_| Msg::Undo);
+            let redo_cb = ctx.link().callback(|_| Msg::Redo);
+            let window = web_sys::window().unwrap();
+            self.keydown_listener = Some(EventListener::new(&window, "keydown", move |event| {
+                let event: &KeyboardEvent = event.dyn_ref().unwrap();
+                // Leave text fields their own undo.
+                if event
+                    .target()
+                    .and_then(|target| target.dyn_into::<HtmlInputElement>().ok())
+                    .is_some()
+                {
+                    return;
+                }
+                if !(event.ctrl_key() || event.meta_key()) {
+                    return;
+                }
+
+                match event.key().to_lowercase().as_str() {
+                    "z" if event.shift_key() => redo_cb.emit(()),
+                    "z" => undo_cb.emit(()),
+                    "y" => redo_cb.emit(()),
+                    _ => return,
+                }
+                event.prevent_default();
+            }));
+
             self.canvas_ctx = Some(
                 self.canvas_ref
                     .cast::<HtmlCanvasElement>()
@@ -183,11 +1472,12 @@ impl Component for ImageComponent {
             );
         }
 
-        let width = self.image_to_display.get_width();
-        let height = self.image_to_display.get_height();
+        let image = self.preview.as_ref().unwrap_or(&self.image_to_display);
+        let width = image.get_width();
+        let height = image.get_height();
         let canvas_ctx = self.canvas_ctx.as_ref().unwrap();
         let image_data = ImageData::new_with_u8_clamped_array_and_sh(
-            Clamped(self.image_to_display.get_data_ref()),
+            Clamped(image.get_data_ref()),
             width,
             height,
         )
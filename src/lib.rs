@@ -0,0 +1,6 @@
+pub mod history;
+pub mod image;
+pub mod metrics;
+pub mod pipeline;
+pub mod report;
+pub mod threshold;
@@ -1,19 +1,28 @@
-mod image;
+mod channel_component;
+mod criterion_component;
+mod download;
+mod histogram_component;
 mod image_component;
+mod metrics_component;
+mod montage_component;
+mod shared_image;
+mod tone_component;
+mod upload;
 
-use gloo_events::EventListener;
+use binhis::image::Image;
 use image_component::ImageComponent;
-use js_sys::Uint8Array;
-use wasm_bindgen::JsCast;
+use upload::upload;
 use yew::prelude::*;
 
 enum Msg {
     FileUpload(Event),
     FileLoaded(Vec<u8>),
+    ReadFailed,
 }
 
 struct App {
-    image_data: Option<Vec<u8>>,
+    image: Option<Image>,
+    error: Option<String>,
     is_loading: bool,
 }
 
@@ -23,7 +32,8 @@ impl Component for App {
 
     fn create(_ctx: &Context<Self>) -> Self {
         Self {
-            image_data: None,
+            image: None,
+            error: None,
             is_loading: false,
         }
     }
@@ -36,11 +46,12 @@ impl Component for App {
                     if self.is_loading {
                         <span>{"Loading image..."}</span>
                     }
+                    if let Some(error) = &self.error {
+                        <span style="color: red;">{error}</span>
+                    }
                 </div>
-                if self.image_data.is_some() {
-                    <ImageComponent
-                        image_data={self.image_data.as_ref().unwrap().clone()}
-                    />
+                if let Some(image) = &self.image {
+                    <ImageComponent image={image.clone()} />
                 }
             </>
         }
@@ -49,29 +60,35 @@ impl Component for App {
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::FileUpload(event) => {
-                self.is_loading = true;
                 let file_cb = ctx.link().callback(|value: Vec<u8>| Msg::FileLoaded(value));
-                let target = event.target().unwrap();
-                let target: web_sys::HtmlInputElement = target.dyn_into().unwrap();
-                let file = target.files().unwrap().get(0).unwrap();
-                let file_reader = web_sys::FileReader::new().unwrap();
-                file_reader.read_as_array_buffer(&file).unwrap();
-                let listener = EventListener::new(&file_reader, "load", move |event| {
-                    let target = event.target().unwrap();
-                    let target: web_sys::FileReader = target.dyn_into().unwrap();
-                    let result = target.result().unwrap();
-                    let array = Uint8Array::new(&result);
+                let error_cb = ctx.link().callback(|_| Msg::ReadFailed);
+                if !upload(&event, file_cb, error_cb) {
+                    return false;
+                }
 
-                    file_cb.emit(array.to_vec());
-                });
-                listener.forget();
+                self.is_loading = true;
+                self.error = None;
 
                 true
             }
             Msg::FileLoaded(data) => {
-                log::info!("Image loaded");
                 self.is_loading = false;
-                self.image_data = Some(data);
+                match Image::new_with_data(data) {
+                    Ok(image) => {
+                        log::info!("Image loaded");
+                        self.image = Some(image);
+                    }
+                    Err(error) => {
+                        log::error!("{}", error);
+                        self.error = Some(error.to_string());
+                    }
+                }
+
+                true
+            }
+            Msg::ReadFailed => {
+                self.is_loading = false;
+                self.error = Some("Couldn't read file.".to_string());
 
                 true
             }
@@ -0,0 +1,275 @@
+use std::fmt;
+
+use crate::image::{skeletonize, Image};
+
+/// Side of the blocks DRD counts non-uniform ground-truth regions in.
+const DRD_BLOCK_SIZE: usize = 8;
+/// Side of the neighbourhood weighting each flipped pixel in DRD.
+const DRD_WINDOW: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetricsError {
+    DimensionMismatch {
+        expected: (u32, u32),
+        found: (u32, u32),
+    },
+}
+
+impl fmt::Display for MetricsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetricsError::DimensionMismatch { expected, found } => write!(
+                f,
+                "Ground truth is {}x{} but the image is {}x{}.",
+                expected.0, expected.1, found.0, found.1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MetricsError {}
+
+/// Scores of a binarized image against a ground-truth mask, following the
+/// DIBCO evaluation. Dark pixels (luminance below 128) are foreground in
+/// both images. Ratios are in 0.0..=1.0, PSNR is in dB and infinite for a
+/// perfect match.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BinarizationMetrics {
+    pub precision: f64,
+    pub recall: f64,
+    pub f_measure: f64,
+    /// Recall against the skeleton of the ground-truth foreground, so stroke
+    /// width errors matter less than missing strokes.
+    pub pseudo_recall: f64,
+    pub pseudo_f_measure: f64,
+    pub psnr: f64,
+    /// Distance reciprocal distortion, lower is better.
+    pub drd: f64,
+    /// Fraction of pixels assigned to the wrong class.
+    pub misclassification: f64,
+}
+
+impl BinarizationMetrics {
+    pub fn compute(result: &Image, ground_truth: &Image) -> Result<Self, MetricsError> {
+        let (width, height) = (ground_truth.get_width(), ground_truth.get_height());
+        if (result.get_width(), result.get_height()) != (width, height) {
+            return Err(MetricsError::DimensionMismatch {
+                expected: (width, height),
+                found: (result.get_width(), result.get_height()),
+            });
+        }
+
+        let (width, height) = (width as usize, height as usize);
+        let result = result.foreground_mask();
+        let ground_truth = ground_truth.foreground_mask();
+        let skeleton = skeletonize(&ground_truth, width, height);
+
+        let (mut true_positives, mut false_positives, mut false_negatives) = (0, 0, 0);
+        let mut skeleton_hits = 0;
+        for i in 0..result.len() {
+            match (result[i], ground_truth[i]) {
+                (true, true) => true_positives += 1,
+                (true, false) => false_positives += 1,
+                (false, true) => false_negatives += 1,
+                (false, false) => {}
+            }
+            if result[i] && skeleton[i] {
+                skeleton_hits += 1;
+            }
+        }
+
+        let ratio = |a: usize, b: usize| if b > 0 { a as f64 / b as f64 } else { 0.0 };
+        let harmonic_mean = |a: f64, b: f64| {
+            if a + b > 0.0 {
+                2.0 * a * b / (a + b)
+            } else {
+                0.0
+            }
+        };
+
+        let precision = ratio(true_positives, true_positives + false_positives);
+        let recall = ratio(true_positives, true_positives + false_negatives);
+        let pseudo_recall = ratio(skeleton_hits, skeleton.iter().filter(|&&x| x).count());
+        let misclassification = ratio(false_positives + false_negatives, result.len());
+
+        Ok(Self {
+            precision,
+            recall,
+            f_measure: harmonic_mean(precision, recall),
+            pseudo_recall,
+            pseudo_f_measure: harmonic_mean(precision, pseudo_recall),
+            psnr: 10.0 * (1.0 / misclassification).log10(),
+            drd: drd(&result, &ground_truth, width, height),
+            misclassification,
+        })
+    }
+}
+
+/// Lu, Kot and Shi's distance reciprocal distortion: every flipped pixel
+/// costs the inverse-distance weighted share of its ground-truth
+/// neighbourhood it disagrees with, normalized by the number of
+/// non-uniform blocks in the ground truth.
+fn drd(result: &[bool], ground_truth: &[bool], width: usize, height: usize) -> f64 {
+    let center = DRD_WINDOW / 2;
+    let mut weights = [[0.0; DRD_WINDOW]; DRD_WINDOW];
+    for (y, row) in weights.iter_mut().enumerate() {
+        for (x, weight) in row.iter_mut().enumerate() {
+            if (x, y) != (center, center) {
+                *weight = 1.0
+                    / (x.abs_diff(center).pow(2) as f64 + y.abs_diff(center).pow(2) as f64).sqrt();
+            }
+        }
+    }
+    let total: f64 = weights.iter().flatten().sum();
+    weights
+        .iter_mut()
+        .flatten()
+        .for_each(|weight| *weight /= total);
+
+    let mut distortion = 0.0;
+    for y in 0..height {
+        for x in 0..width {
+            let value = result[y * width + x];
+            if value == ground_truth[y * width + x] {
+                continue;
+            }
+
+            for (wy, row) in weights.iter().enumerate() {
+                for (wx, weight) in row.iter().enumerate() {
+                    let (nx, ny) = ((x + wx).wrapping_sub(center), (y + wy).wrapping_sub(center));
+                    if nx < width && ny < height && ground_truth[ny * width + nx] != value {
+                        distortion += weight;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut non_uniform_blocks = 0;
+    for by in (0..height).step_by(DRD_BLOCK_SIZE) {
+        for bx in (0..width).step_by(DRD_BLOCK_SIZE) {
+            let mut block = (by..(by + DRD_BLOCK_SIZE).min(height)).flat_map(|y| {
+                (bx..(bx + DRD_BLOCK_SIZE).min(width)).map(move |x| ground_truth[y * width + x])
+            });
+            let first = block.next().unwrap();
+            if block.any(|value| value != first) {
+                non_uniform_blocks += 1;
+            }
+        }
+    }
+
+    distortion / non_uniform_blocks.max(1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{DynamicImage, ImageOutputFormat, Luma};
+
+    use super::*;
+
+    const WIDTH: u32 = 16;
+    const HEIGHT: u32 = 8;
+
+    fn image(width: u32, height: u32, foreground: impl Fn(u32, u32) -> bool) -> Image {
+        let pixels = image::GrayImage::from_fn(width, height, |x, y| {
+            Luma([if foreground(x, y) { 0 } else { 255 }])
+        });
+        let mut png = Cursor::new(Vec::new());
+        DynamicImage::ImageLuma8(pixels)
+            .write_to(&mut png, ImageOutputFormat::Png)
+            .unwrap();
+
+        Image::new_with_data(png.into_inner()).unwrap()
+    }
+
+    /// Two 8x8 blocks, the left one blank and the right one half covered by
+    /// a 4 pixel wide bar, so exactly one block is non-uniform.
+    fn bar(x: u32, _y: u32) -> bool {
+        (8..12).contains(&x)
+    }
+
+    /// `bar` with the pixel at `flipped` turned to foreground.
+    fn bar_with(flipped: (u32, u32)) -> Image {
+        image(WIDTH, HEIGHT, |x, y| bar(x, y) || (x, y) == flipped)
+    }
+
+    #[test]
+    fn perfect_match() {
+        let ground_truth = image(WIDTH, HEIGHT, bar);
+        let metrics = BinarizationMetrics::compute(&ground_truth, &ground_truth).unwrap();
+
+        assert_eq!(metrics.f_measure, 1.0);
+        assert_eq!(metrics.pseudo_f_measure, 1.0);
+        assert_eq!(metrics.drd, 0.0);
+        assert_eq!(metrics.misclassification, 0.0);
+        assert_eq!(metrics.psnr, f64::INFINITY);
+    }
+
+    #[test]
+    fn single_flipped_pixel_inside_a_uniform_block() {
+        let ground_truth = image(WIDTH, HEIGHT, bar);
+        let metrics = BinarizationMetrics::compute(&bar_with((3, 3)), &ground_truth).unwrap();
+
+        // 32 bar pixels found and one false positive.
+        assert_eq!(metrics.precision, 32.0 / 33.0);
+        assert_eq!(metrics.recall, 1.0);
+        assert!((metrics.f_measure - 64.0 / 65.0).abs() < 1e-12);
+        assert!((metrics.psnr - 10.0 * 128f64.log10()).abs() < 1e-12);
+        // The whole 5x5 window disagrees with the flipped pixel, so it costs
+        // the sum of the normalized weights, over one non-uniform block.
+        assert!((metrics.drd - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn single_flipped_pixel_in_a_corner() {
+        let ground_truth = image(WIDTH, HEIGHT, bar);
+        let metrics = BinarizationMetrics::compute(&bar_with((0, 0)), &ground_truth).unwrap();
+
+        // Reciprocal distances to the center of the 5x5 window: 4 cells at
+        // 1, 4 at sqrt(2), 4 at 2, 8 at sqrt(5) and 4 at sqrt(8). From the
+        // corner only the lower right quarter is inside the image.
+        let weight = |d2: f64| 1.0 / d2.sqrt();
+        let total = 4.0 * weight(1.0)
+            + 4.0 * weight(2.0)
+            + 4.0 * weight(4.0)
+            + 8.0 * weight(5.0)
+            + 4.0 * weight(8.0);
+        let inside =
+            2.0 * weight(1.0) + weight(2.0) + 2.0 * weight(4.0) + 2.0 * weight(5.0) + weight(8.0);
+        assert!((metrics.drd - inside / total).abs() < 1e-12);
+    }
+
+    #[test]
+    fn skeleton_alone_has_full_pseudo_recall() {
+        let ground_truth = image(WIDTH, HEIGHT, bar);
+        let mask: Vec<bool> = (0..WIDTH * HEIGHT)
+            .map(|i| bar(i % WIDTH, i / WIDTH))
+            .collect();
+        let skeleton = skeletonize(&mask, WIDTH as usize, HEIGHT as usize);
+        let strokes = skeleton.iter().filter(|&&x| x).count();
+        let result = image(WIDTH, HEIGHT, |x, y| skeleton[(y * WIDTH + x) as usize]);
+        let metrics = BinarizationMetrics::compute(&result, &ground_truth).unwrap();
+
+        assert!(strokes > 0 && strokes < 32);
+        assert_eq!(metrics.precision, 1.0);
+        assert_eq!(metrics.recall, strokes as f64 / 32.0);
+        assert_eq!(metrics.pseudo_recall, 1.0);
+        assert_eq!(metrics.pseudo_f_measure, 1.0);
+    }
+
+    #[test]
+    fn mismatched_dimensions() {
+        let ground_truth = image(WIDTH, HEIGHT, bar);
+        let result = image(8, 8, bar);
+
+        assert_eq!(
+            BinarizationMetrics::compute(&result, &ground_truth),
+            Err(MetricsError::DimensionMismatch {
+                expected: (16, 8),
+                found: (8, 8),
+            })
+        );
+    }
+}
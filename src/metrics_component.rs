@@ -0,0 +1,204 @@
+use binhis::{
+    image::Image,
+    metrics::{BinarizationMetrics, MetricsError},
+    threshold::ThresholdMethod,
+};
+use web_sys::Event;
+use yew::{html, Component, Properties};
+
+use crate::{shared_image::SharedImage, upload::upload};
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub image: SharedImage,
+    pub black_percent: f32,
+}
+
+pub enum Msg {
+    GroundTruthUpload(Event),
+    GroundTruthLoaded(Vec<u8>),
+    ReadFailed,
+    ScoreMethods,
+}
+
+/// Scores the current output against an uploaded ground-truth mask.
+pub struct MetricsComponent {
+    ground_truth: Option<Image>,
+    error: Option<String>,
+    metrics: Option<Result<BinarizationMetrics, MetricsError>>,
+    method_scores: Vec<(&'static str, BinarizationMetrics)>,
+}
+
+impl MetricsComponent {
+    fn evaluate(&mut self, ctx: &yew::Context<Self>) {
+        self.metrics = self
+            .ground_truth
+            .as_ref()
+            .map(|ground_truth| BinarizationMetrics::compute(&ctx.props().image, ground_truth));
+        self.method_scores.clear();
+    }
+
+    fn view_metrics(metrics: &BinarizationMetrics) -> yew::Html {
+        let rows = [
+            ("F-measure", format!("{:.2}%", metrics.f_measure * 100.0)),
+            (
+                "Pseudo F-measure",
+                format!("{:.2}%", metrics.pseudo_f_measure * 100.0),
+            ),
+            ("Precision", format!("{:.2}%", metrics.precision * 100.0)),
+            ("Recall", format!("{:.2}%", metrics.recall * 100.0)),
+            (
+                "Pseudo recall",
+                format!("{:.2}%", metrics.pseudo_recall * 100.0),
+            ),
+            ("PSNR", format!("{:.2} dB", metrics.psnr)),
+            ("DRD", format!("{:.3}", metrics.drd)),
+            (
+                "Misclassification error",
+                format!("{:.2}%", metrics.misclassification * 100.0),
+            ),
+        ];
+
+        html! {
+            <table>
+                { for rows.iter().map(|(name, value)| html! {
+                    <tr>
+                        <td>{name}</td>
+                        <td>{value}</td>
+                    </tr>
+                }) }
+            </table>
+        }
+    }
+
+    fn view_method_scores(&self) -> yew::Html {
+        if self.method_scores.is_empty() {
+            return html! {};
+        }
+
+        html! {
+            <table>
+                <tr>
+                    <th>{"Method"}</th>
+                    <th>{"F-measure"}</th>
+                    <th>{"Pseudo F-measure"}</th>
+                    <th>{"PSNR"}</th>
+                    <th>{"DRD"}</th>
+                </tr>
+                { for self.method_scores.iter().map(|(name, metrics)| html! {
+                    <tr>
+                        <td>{name}</td>
+                        <td>{format!("{:.2}%", metrics.f_measure * 100.0)}</td>
+                        <td>{format!("{:.2}%", metrics.pseudo_f_measure * 100.0)}</td>
+                        <td>{format!("{:.2} dB", metrics.psnr)}</td>
+                        <td>{format!("{:.3}", metrics.drd)}</td>
+                    </tr>
+                }) }
+            </table>
+        }
+    }
+}
+
+impl Component for MetricsComponent {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &yew::Context<Self>) -> Self {
+        Self {
+            ground_truth: None,
+            error: None,
+            metrics: None,
+            method_scores: Vec::new(),
+        }
+    }
+
+    fn changed(&mut self, ctx: &yew::Context<Self>) -> bool {
+        self.evaluate(ctx);
+
+        true
+    }
+
+    fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let link = ctx.link();
+
+        html! {
+            <details>
+                <summary>{"Binarization metrics"}</summary>
+                <div>
+                    {"Ground truth "}
+                    <input type="file"
+                        onchange={link.callback(|event: Event| Msg::GroundTruthUpload(event))} />
+                    if let Some(error) = &self.error {
+                        <span style="color: red;">{error}</span>
+                    }
+                </div>
+                {
+                    match &self.metrics {
+                        Some(Ok(metrics)) => html! {
+                            <>
+                                { Self::view_metrics(metrics) }
+                                <button onclick={link.callback(|_| Msg::ScoreMethods)}>
+                                    {"Score every treshold method"}
+                                </button>
+                                { self.view_method_scores() }
+                            </>
+                        },
+                        Some(Err(error)) => html! {
+                            <span style="color: red;">{error.to_string()}</span>
+                        },
+                        None => html! {},
+                    }
+                }
+            </details>
+        }
+    }
+
+    fn update(&mut self, ctx: &yew::Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GroundTruthUpload(event) => {
+                let file_cb = ctx
+                    .link()
+                    .callback(|value: Vec<u8>| Msg::GroundTruthLoaded(value));
+                let error_cb = ctx.link().callback(|_| Msg::ReadFailed);
+                upload(&event, file_cb, error_cb);
+                self.error = None;
+
+                true
+            }
+            Msg::GroundTruthLoaded(data) => {
+                match Image::new_with_data(data) {
+                    Ok(image) => self.ground_truth = Some(image),
+                    Err(error) => {
+                        self.ground_truth = None;
+                        self.error = Some(error.to_string());
+                    }
+                }
+                self.evaluate(ctx);
+
+                true
+            }
+            Msg::ReadFailed => {
+                self.error = Some("Couldn't read file.".to_string());
+
+                true
+            }
+            Msg::ScoreMethods => {
+                let image = &ctx.props().image;
+                if let Some(ground_truth) = &self.ground_truth {
+                    self.method_scores = ThresholdMethod::ALL
+                        .iter()
+                        .map(|method| method.with_black_percent(ctx.props().black_percent))
+                        .filter_map(|method| {
+                            image
+                                .evaluate_threshold(method, ground_truth)
+                                .ok()
+                                .map(|metrics| (method.name(), metrics))
+                        })
+                        .collect();
+                }
+
+                true
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use binhis::{image::Image, threshold::ThresholdMethod};
+use wasm_bindgen::{Clamped, JsCast};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
+use yew::{html, Callback, Component, NodeRef, Properties};
+
+use crate::shared_image::SharedImage;
+
+const THUMBNAIL_SIZE: u32 = 160;
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub image: SharedImage,
+    pub black_percent: f32,
+    pub on_promote: Callback<ThresholdMethod>,
+}
+
+struct Tile {
+    method: ThresholdMethod,
+    threshold: u8,
+    image: Image,
+    canvas_ref: NodeRef,
+}
+
+/// Grid of thumbnails binarized by every global selection method, each
+/// labelled with the level it picked. Thresholds are computed on the full
+/// image so they match what promoting a tile applies.
+pub struct MontageComponent {
+    tiles: Vec<Tile>,
+    /// Image and black percent the tiles were computed for.
+    source: (SharedImage, f32),
+}
+
+impl MontageComponent {
+    fn tiles(props: &Props) -> Vec<Tile> {
+        let histogram = props.image.get_grayscale_histogram();
+        let thumbnail = props.image.thumbnail(THUMBNAIL_SIZE);
+
+        ThresholdMethod::ALL
+            .iter()
+            .map(|method| method.with_black_percent(props.black_percent))
+            .map(|method| {
+                let threshold = method.compute(&histogram).threshold;
+                Tile {
+                    method,
+                    threshold,
+                    image: thumbnail.threshold((threshold, 255)),
+                    canvas_ref: NodeRef::default(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Component for MontageComponent {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(ctx: &yew::Context<Self>) -> Self {
+        let props = ctx.props();
+        Self {
+            tiles: Self::tiles(props),
+            source: (props.image.clone(), props.black_percent),
+        }
+    }
+
+    fn changed(&mut self, ctx: &yew::Context<Self>) -> bool {
+        // The promote callback is new on every render of the parent, only
+        // rebuild the tiles when what they show changed.
+        let props = ctx.props();
+        let source = (props.image.clone(), props.black_percent);
+        if source == self.source {
+            return false;
+        }
+        self.tiles = Self::tiles(props);
+        self.source = source;
+
+        true
+    }
+
+    fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        html! {
+            <div style="display: flex; flex-wrap: wrap; gap: 8px;">
+                { for self.tiles.iter().map(|tile| {
+                    let method = tile.method;
+                    let on_promote = ctx.props().on_promote.clone();
+                    html! {
+                        <figure style="margin: 0; cursor: pointer;" title="Apply this treshold"
+                            onclick={move |_| on_promote.emit(method)}>
+                            <canvas ref={tile.canvas_ref.clone()}
+                                width={tile.image.get_width().to_string()}
+                                height={tile.image.get_height().to_string()}
+                            />
+                            <figcaption>
+                                {format!("{}: {}", tile.method.name(), tile.threshold)}
+                            </figcaption>
+                        </figure>
+                    }
+                }) }
+            </div>
+        }
+    }
+
+    fn rendered(&mut self, _ctx: &yew::Context<Self>, _first_render: bool) {
+        for tile in &self.tiles {
+            let canvas_ctx = match tile.canvas_ref.cast::<HtmlCanvasElement>() {
+                Some(canvas) => canvas
+                    .get_context("2d")
+                    .unwrap()
+                    .unwrap()
+                    .dyn_into::<CanvasRenderingContext2d>()
+                    .unwrap(),
+                None => continue,
+            };
+            let image_data = ImageData::new_with_u8_clamped_array_and_sh(
+                Clamped(tile.image.get_data_ref()),
+                tile.image.get_width(),
+                tile.image.get_height(),
+            )
+            .unwrap();
+
+            canvas_ctx
+                .put_image_data(&image_data, 0.0, 0.0)
+                .expect("Couldn't draw image");
+        }
+    }
+}
@@ -0,0 +1,375 @@
+use std::fmt;
+
+use crate::{
+    image::{
+        AdaptiveMethod, Adjustment, BinaryMorphology, Curve, GrayMorphology, HistogramTarget,
+        Image, Intensity, Levels, Lut, RgbHistograms, StructuringElement, ThresholdMode,
+    },
+    threshold::ThresholdMethod,
+};
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Operation {
+    Stretch(Intensity),
+    Equalize(Intensity),
+    StretchClipped {
+        clip: (f32, f32),
+        intensity: Intensity,
+        linked: bool,
+    },
+    Clahe {
+        grid: (u32, u32),
+        clip_limit: f32,
+        intensity: Intensity,
+    },
+    MatchHistogram {
+        target: HistogramTarget,
+        intensity: Intensity,
+    },
+    Levels {
+        levels: Levels,
+        intensity: Intensity,
+    },
+    Curve {
+        curve: Curve,
+        intensity: Intensity,
+    },
+    Adjust {
+        adjustment: Adjustment,
+        intensity: Intensity,
+    },
+    Threshold(ThresholdMode),
+    AutoThreshold(ThresholdMethod),
+    MultiOtsu(usize),
+    Adaptive {
+        window: u32,
+        method: AdaptiveMethod,
+    },
+    BinaryMorphology {
+        operation: BinaryMorphology,
+        element: StructuringElement,
+    },
+    GrayMorphology {
+        operation: GrayMorphology,
+        element: StructuringElement,
+        intensity: Intensity,
+    },
+}
+
+impl Operation {
+    pub fn apply(&self, image: &Image) -> Image {
+        match self {
+            Operation::Stretch(intensity) => image.stretch_intensity(*intensity),
+            Operation::Equalize(intensity) => image.equalize_intensity(*intensity),
+            Operation::StretchClipped {
+                clip,
+                intensity,
+                linked,
+            } => image.stretch_clipped(*clip, *intensity, *linked),
+            Operation::Clahe {
+                grid,
+                clip_limit,
+                intensity,
+            } => image.clahe(*grid, *clip_limit, *intensity),
+            Operation::MatchHistogram { target, intensity } => {
+                image.match_histogram(target, *intensity)
+            }
+            Operation::Levels { levels, intensity } => image.apply_levels(levels, *intensity),
+            Operation::Curve { curve, intensity } => image.apply_curve(curve, *intensity),
+            Operation::Adjust {
+                adjustment,
+                intensity,
+            } => image.adjust(*adjustment, *intensity),
+            Operation::Threshold(mode) => image.threshold_with(*mode),
+            Operation::AutoThreshold(method) => image.auto_threshold(*method),
+            Operation::MultiOtsu(count) => image.multi_otsu_selection(*count),
+            Operation::Adaptive { window, method } => image.adaptive_threshold(*window, *method),
+            Operation::BinaryMorphology { operation, element } => {
+                image.binary_morphology(*operation, element)
+            }
+            Operation::GrayMorphology {
+                operation,
+                element,
+                intensity,
+            } => image.gray_morphology(*operation, element, *intensity),
+        }
+    }
+
+    /// True if the operation remaps red, green and blue each on their own,
+    /// so it can be expressed as a `Lut`.
+    pub fn is_point_operation(&self) -> bool {
+        match self {
+            Operation::Stretch(intensity)
+            | Operation::Equalize(intensity)
+            | Operation::StretchClipped { intensity, .. }
+            | Operation::MatchHistogram { intensity, .. }
+            | Operation::Levels { intensity, .. }
+            | Operation::Curve { intensity, .. }
+            | Operation::Adjust { intensity, .. } => *intensity == Intensity::PerChannel,
+            Operation::Threshold(mode) => mode.lut().is_some(),
+            _ => false,
+        }
+    }
+
+    /// The operation as a lookup table for an input with `histograms`, or
+    /// None if it isn't a point operation.
+    pub fn lut(&self, histograms: &RgbHistograms) -> Option<Lut> {
+        if !self.is_point_operation() {
+            return None;
+        }
+
+        match self {
+            Operation::Stretch(_) => Some(Lut::stretch(histograms)),
+            Operation::Equalize(_) => Some(Lut::equalization(histograms)),
+            Operation::StretchClipped { clip, linked, .. } => {
+                Some(Lut::clipped_stretch(histograms, *clip, *linked))
+            }
+            Operation::MatchHistogram { target, .. } => {
+                Some(Lut::histogram_match(histograms, target))
+            }
+            Operation::Levels { levels, .. } => Some(Lut::uniform(levels.lut())),
+            Operation::Curve { curve, .. } => Some(Lut::uniform(curve.lut())),
+            Operation::Adjust { adjustment, .. } => Some(Lut::uniform(adjustment.lut())),
+            Operation::Threshold(mode) => mode.lut(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Stretch(Intensity::PerChannel) => write!(f, "Stretch histogram"),
+            Operation::Stretch(intensity) => write!(f, "Stretch histogram ({})", intensity.name()),
+            Operation::Equalize(Intensity::PerChannel) => write!(f, "Equalize histogram"),
+            Operation::Equalize(intensity) => {
+                write!(f, "Equalize histogram ({})", intensity.name())
+            }
+            Operation::StretchClipped {
+                clip: (low, high),
+                intensity,
+                linked,
+            } => write!(
+                f,
+                "Stretch histogram (clip {:.2}% / {:.2}%, {}{})",
+                low * 100.0,
+                high * 100.0,
+                intensity.name(),
+                if *linked { ", linked" } else { "" }
+            ),
+            Operation::Clahe {
+                grid: (columns, rows),
+                clip_limit,
+                intensity,
+            } => write!(
+                f,
+                "CLAHE ({}x{} tiles, clip limit {}, {})",
+                columns,
+                rows,
+                clip_limit,
+                intensity.name()
+            ),
+            Operation::MatchHistogram { target, intensity } => {
+                write!(f, "Match histogram ({}, {})", target, intensity.name())
+            }
+            Operation::Levels { levels, intensity } => {
+                write!(f, "Levels ({}, {})", levels, intensity.name())
+            }
+            Operation::Curve { curve, intensity } => {
+                write!(f, "Curve ({}, {})", curve, intensity.name())
+            }
+            Operation::Adjust {
+                adjustment,
+                intensity,
+            } => write!(f, "{} ({})", adjustment, intensity.name()),
+            Operation::Threshold(ThresholdMode::Any { low, high }) => {
+                write!(f, "Threshold [{}, {}]", low, high)
+            }
+            Operation::Threshold(mode) => write!(f, "Threshold ({})", mode),
+            Operation::AutoThreshold(ThresholdMethod::PercentBlack(percent)) => {
+                write!(f, "Percent Black Selection ({:.2}%)", percent * 100.0)
+            }
+            Operation::AutoThreshold(method) => write!(f, "{}", method.name()),
+            Operation::MultiOtsu(count) => write!(f, "Multi-level Otsu ({} thresholds)", count),
+            Operation::Adaptive { window, method } => {
+                write!(f, "{} ({}x{} window)", method.name(), window, window)
+            }
+            Operation::BinaryMorphology { operation, element } if operation.uses_element() => {
+                write!(f, "{} ({})", operation.name(), element)
+            }
+            Operation::BinaryMorphology { operation, .. } => write!(f, "{}", operation.name()),
+            Operation::GrayMorphology {
+                operation,
+                element,
+                intensity,
+            } => write!(
+                f,
+                "Grayscale {} ({}, {})",
+                operation.name().to_lowercase(),
+                element,
+                intensity.name()
+            ),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Step {
+    pub operation: Operation,
+    pub enabled: bool,
+}
+
+/// Ordered list of operations, each applied to the output of the previous
+/// enabled one.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Pipeline {
+    steps: Vec<Step>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn push(&mut self, operation: Operation) {
+        self.steps.push(Step {
+            operation,
+            enabled: true,
+        });
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.steps.len() {
+            self.steps.remove(index);
+        }
+    }
+
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(step) = self.steps.get_mut(index) {
+            step.enabled = !step.enabled;
+        }
+    }
+
+    pub fn move_up(&mut self, index: usize) {
+        if index > 0 && index < self.steps.len() {
+            self.steps.swap(index - 1, index);
+        }
+    }
+
+    pub fn move_down(&mut self, index: usize) {
+        if index + 1 < self.steps.len() {
+            self.steps.swap(index, index + 1);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.steps.clear();
+    }
+
+    /// Applies the enabled steps in order. Runs of point operations are
+    /// composed into a single `Lut`, each one built from the histograms the
+    /// previous ones would produce, and applied in one pass.
+    pub fn run(&self, image: &Image) -> Image {
+        let mut image = image.clone();
+        let mut pending: Option<(Lut, RgbHistograms)> = None;
+
+        for step in self.steps.iter().filter(|step| step.enabled) {
+            let operation = &step.operation;
+            if operation.is_point_operation() {
+                let (lut, histograms) = pending
+                    .take()
+                    .unwrap_or_else(|| (Lut::identity(), image.get_rgb_histograms()));
+                if let Some(next) = operation.lut(&histograms) {
+                    pending = Some((lut.then(&next), next.map_histograms(&histograms)));
+                    continue;
+                }
+                pending = Some((lut, histograms));
+            }
+
+            if let Some((lut, _)) = pending.take() {
+                image = image.apply_lut(&lut);
+            }
+            image = operation.apply(&image);
+        }
+
+        match pending {
+            Some((lut, _)) => image.apply_lut(&lut),
+            None => image,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{DynamicImage, ImageOutputFormat, Rgba, RgbaImage};
+
+    use super::*;
+
+    fn image() -> Image {
+        let pixels = RgbaImage::from_fn(31, 19, |x, y| {
+            Rgba([
+                (x * 7 + y * 3) as u8,
+                (x * y % 180 + 40) as u8,
+                (x * 13 % 90 + y) as u8,
+                255,
+            ])
+        });
+        let mut png = Cursor::new(Vec::new());
+        DynamicImage::ImageRgba8(pixels)
+            .write_to(&mut png, ImageOutputFormat::Png)
+            .unwrap();
+
+        Image::new_with_data(png.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn fused_point_operations_match_applying_each_step() {
+        let operations = [
+            Operation::Stretch(Intensity::PerChannel),
+            Operation::Levels {
+                levels: Levels {
+                    input: (20, 230),
+                    gamma: 1.4,
+                    output: (10, 250),
+                },
+                intensity: Intensity::PerChannel,
+            },
+            Operation::Equalize(Intensity::PerChannel),
+            // Not a point operation, ends the first run of tables.
+            Operation::Equalize(Intensity::Luma),
+            Operation::StretchClipped {
+                clip: (0.02, 0.02),
+                intensity: Intensity::PerChannel,
+                linked: false,
+            },
+            Operation::MatchHistogram {
+                target: HistogramTarget::ALL[0].clone(),
+                intensity: Intensity::PerChannel,
+            },
+            Operation::Adjust {
+                adjustment: Adjustment::Gamma(0.7),
+                intensity: Intensity::PerChannel,
+            },
+        ];
+
+        let image = image();
+        let mut pipeline = Pipeline::new();
+        let mut expected = image.clone();
+        for operation in operations {
+            expected = operation.apply(&expected);
+            pipeline.push(operation);
+        }
+
+        assert!(pipeline.run(&image) == expected);
+    }
+}
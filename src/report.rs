@@ -0,0 +1,221 @@
+use std::fmt::Write;
+
+use crate::{
+    image::{ColorComponent, Image},
+    threshold::ThresholdMethod,
+};
+
+struct ImageStatistics {
+    red: [u32; 256],
+    green: [u32; 256],
+    blue: [u32; 256],
+    grayscale: [u32; 256],
+    thresholds: Vec<(&'static str, u8)>,
+}
+
+impl ImageStatistics {
+    fn of(image: &Image, black_percent: f32) -> Self {
+        let histogram = image.get_histogram();
+        let grayscale = image.get_grayscale_histogram();
+
+        Self {
+            red: histogram[&ColorComponent::Red],
+            green: histogram[&ColorComponent::Green],
+            blue: histogram[&ColorComponent::Blue],
+            grayscale,
+            thresholds: ThresholdMethod::ALL
+                .iter()
+                .map(|method| method.with_black_percent(black_percent))
+                .map(|method| (method.key(), method.compute(&grayscale).threshold))
+                .collect(),
+        }
+    }
+
+    fn channels(&self) -> [(&'static str, &[u32; 256]); 4] {
+        [
+            ("red", &self.red),
+            ("green", &self.green),
+            ("blue", &self.blue),
+            ("grayscale", &self.grayscale),
+        ]
+    }
+
+    fn write_json(&self, json: &mut String) {
+        json.push_str("{\"histograms\":{");
+        for (i, (name, histogram)) in self.channels().iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            let bins: Vec<String> = histogram.iter().map(u32::to_string).collect();
+            write!(json, "\"{}\":[{}]", name, bins.join(",")).unwrap();
+        }
+        json.push_str("},\"thresholds\":{");
+        for (i, (method, threshold)) in self.thresholds.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            write!(json, "\"{}\":{}", method, threshold).unwrap();
+        }
+        json.push_str("}}");
+    }
+}
+
+/// Histogram bins of an image before and after processing, together with the
+/// level every global selection method picks for each of them.
+pub struct Report {
+    original: ImageStatistics,
+    processed: ImageStatistics,
+    black_percent: f32,
+}
+
+impl Report {
+    /// `black_percent` is the fraction used by the percent black selection.
+    pub fn new(original: &Image, processed: &Image, black_percent: f32) -> Self {
+        Self {
+            original: ImageStatistics::of(original, black_percent),
+            processed: ImageStatistics::of(processed, black_percent),
+            black_percent,
+        }
+    }
+
+    /// One row per gray level with a column per image and channel.
+    pub fn histograms_csv(&self) -> String {
+        let mut csv = String::from("level");
+        for (image, statistics) in [("original", &self.original), ("processed", &self.processed)] {
+            for (channel, _) in statistics.channels() {
+                write!(csv, ",{}_{}", image, channel).unwrap();
+            }
+        }
+        csv.push('\n');
+
+        for level in 0..256 {
+            write!(csv, "{}", level).unwrap();
+            for statistics in [&self.original, &self.processed] {
+                for (_, histogram) in statistics.channels() {
+                    write!(csv, ",{}", histogram[level]).unwrap();
+                }
+            }
+            csv.push('\n');
+        }
+
+        csv
+    }
+
+    /// One row per selection method with the level picked on each image.
+    pub fn thresholds_csv(&self) -> String {
+        let mut csv = String::from("method,original,processed\n");
+        for ((method, original), (_, processed)) in self
+            .original
+            .thresholds
+            .iter()
+            .zip(self.processed.thresholds.iter())
+        {
+            writeln!(csv, "{},{},{}", method, original, processed).unwrap();
+        }
+
+        csv
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        write!(
+            json,
+            "{{\"black_percent\":{},\"original\":",
+            self.black_percent
+        )
+        .unwrap();
+        self.original.write_json(&mut json);
+        json.push_str(",\"processed\":");
+        self.processed.write_json(&mut json);
+        json.push('}');
+
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{DynamicImage, ImageOutputFormat, Rgba, RgbaImage};
+
+    use super::*;
+
+    fn image(seed: u32) -> Image {
+        let pixels = RgbaImage::from_fn(16, 8, |x, y| {
+            let i = y * 16 + x + seed;
+            let level = |k: u32| ((i * k + i * i * 104_729) % 256) as u8;
+            Rgba([level(7919), level(31), level(257), 255])
+        });
+        let mut png = Cursor::new(Vec::new());
+        DynamicImage::ImageRgba8(pixels)
+            .write_to(&mut png, ImageOutputFormat::Png)
+            .unwrap();
+
+        Image::new_with_data(png.into_inner()).unwrap()
+    }
+
+    fn report() -> Report {
+        Report::new(&image(0), &image(11), 0.5)
+    }
+
+    #[test]
+    fn histograms_csv_has_a_row_per_level() {
+        let csv = report().histograms_csv();
+        let mut lines = csv.lines();
+
+        assert_eq!(
+            lines.next().unwrap(),
+            "level,original_red,original_green,original_blue,original_grayscale,\
+             processed_red,processed_green,processed_blue,processed_grayscale"
+        );
+        let rows: Vec<Vec<u32>> = lines
+            .map(|line| line.split(',').map(|cell| cell.parse().unwrap()).collect())
+            .collect();
+        assert_eq!(rows.len(), 256);
+        for (level, row) in rows.iter().enumerate() {
+            assert_eq!(row.len(), 9);
+            assert_eq!(row[0], level as u32);
+        }
+        // Every column counts all 16x8 pixels.
+        for column in 1..9 {
+            assert_eq!(rows.iter().map(|row| row[column]).sum::<u32>(), 128);
+        }
+    }
+
+    #[test]
+    fn thresholds_csv_has_a_row_per_method() {
+        let csv = report().thresholds_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines[0], "method,original,processed");
+        assert_eq!(lines.len(), ThresholdMethod::ALL.len() + 1);
+        for (line, method) in lines[1..].iter().zip(ThresholdMethod::ALL.iter()) {
+            assert!(line.starts_with(&format!("{},", method.key())));
+            assert_eq!(line.split(',').count(), 3);
+        }
+    }
+
+    #[test]
+    fn json_is_balanced_and_lists_every_method() {
+        let json = report().to_json();
+
+        let mut depth = 0;
+        for c in json.chars() {
+            match c {
+                '{' | '[' => depth += 1,
+                '}' | ']' => depth -= 1,
+                _ => {}
+            }
+            assert!(depth >= 0, "closed more than opened in {}", json);
+        }
+        assert_eq!(depth, 0);
+        assert_eq!(json.matches('{').count(), json.matches('}').count());
+        assert_eq!(json.matches('[').count(), json.matches(']').count());
+
+        // Once for the original image and once for the processed one.
+        for method in ThresholdMethod::ALL.iter() {
+            assert_eq!(json.matches(&format!("\"{}\":", method.key())).count(), 2);
+        }
+    }
+}
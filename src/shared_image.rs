@@ -0,0 +1,29 @@
+use std::{ops::Deref, rc::Rc};
+
+use binhis::image::Image;
+
+/// Image handed down to child components. Clones share the pixels and two
+/// handles are equal only if they point to the same image, so re-rendering
+/// the parent neither copies nor compares the buffer.
+#[derive(Clone)]
+pub struct SharedImage(Rc<Image>);
+
+impl SharedImage {
+    pub fn new(image: Image) -> Self {
+        Self(Rc::new(image))
+    }
+}
+
+impl Deref for SharedImage {
+    type Target = Image;
+
+    fn deref(&self) -> &Image {
+        &self.0
+    }
+}
+
+impl PartialEq for SharedImage {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
@@ -0,0 +1,635 @@
+mod imagej;
+
+pub use imagej::{
+    compute_huang_threshold, compute_intermodes_threshold, compute_isodata_threshold,
+    compute_li_threshold, compute_max_entropy_threshold, compute_mean_threshold,
+    compute_minimum_threshold, compute_moments_threshold, compute_renyi_entropy_threshold,
+    compute_shanbhag_threshold, compute_triangle_threshold, compute_yen_threshold,
+};
+
+/// Threshold picked by a selection method along with the criterion it
+/// optimized, so the choice can be inspected.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ThresholdSelection {
+    pub threshold: u8,
+    /// Value of the criterion for every gray level taken as the threshold,
+    /// NaN where it is undefined.
+    pub criterion: Vec<f32>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ThresholdMethod {
+    PercentBlack(f32),
+    MeanIterative,
+    Entropy,
+    MinimumError,
+    FuzzyMinimumError,
+    Otsu,
+    Huang,
+    Intermodes,
+    IsoData,
+    Li,
+    MaxEntropy,
+    Mean,
+    Minimum,
+    Moments,
+    RenyiEntropy,
+    Shanbhag,
+    Triangle,
+    Yen,
+}
+
+impl ThresholdMethod {
+    pub const ALL: [ThresholdMethod; 18] = [
+        ThresholdMethod::PercentBlack(0.5),
+        ThresholdMethod::MeanIterative,
+        ThresholdMethod::Entropy,
+        ThresholdMethod::MinimumError,
+        ThresholdMethod::FuzzyMinimumError,
+        ThresholdMethod::Otsu,
+        ThresholdMethod::Huang,
+        ThresholdMethod::Intermodes,
+        ThresholdMethod::IsoData,
+        ThresholdMethod::Li,
+        ThresholdMethod::MaxEntropy,
+        ThresholdMethod::Mean,
+        ThresholdMethod::Minimum,
+        ThresholdMethod::Moments,
+        ThresholdMethod::RenyiEntropy,
+        ThresholdMethod::Shanbhag,
+        ThresholdMethod::Triangle,
+        ThresholdMethod::Yen,
+    ];
+
+    /// Replaces the fraction of the percent black selection, other methods
+    /// have no parameters and are returned as they are.
+    pub fn with_black_percent(self, percent: f32) -> Self {
+        match self {
+            ThresholdMethod::PercentBlack(_) => ThresholdMethod::PercentBlack(percent),
+            method => method,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThresholdMethod::PercentBlack(_) => "Percent Black Selection",
+            ThresholdMethod::MeanIterative => "Mean Iterative Selection",
+            ThresholdMethod::Entropy => "Entropy Selection",
+            ThresholdMethod::MinimumError => "Minimum Error",
+            ThresholdMethod::FuzzyMinimumError => "Fuzzy Minimum Error",
+            ThresholdMethod::Otsu => "Otsu",
+            ThresholdMethod::Huang => "Huang",
+            ThresholdMethod::Intermodes => "Intermodes",
+            ThresholdMethod::IsoData => "IsoData",
+            ThresholdMethod::Li => "Li",
+            ThresholdMethod::MaxEntropy => "Maximum Entropy (Kapur)",
+            ThresholdMethod::Mean => "Mean",
+            ThresholdMethod::Minimum => "Minimum",
+            ThresholdMethod::Moments => "Moments",
+            ThresholdMethod::RenyiEntropy => "Renyi Entropy",
+            ThresholdMethod::Shanbhag => "Shanbhag",
+            ThresholdMethod::Triangle => "Triangle",
+            ThresholdMethod::Yen => "Yen",
+        }
+    }
+
+    /// Identifier used in exported reports.
+    pub fn key(&self) -> &'static str {
+        match self {
+            ThresholdMethod::PercentBlack(_) => "percent_black",
+            ThresholdMethod::MeanIterative => "mean_iterative",
+            ThresholdMethod::Entropy => "entropy",
+            ThresholdMethod::MinimumError => "minimum_error",
+            ThresholdMethod::FuzzyMinimumError => "fuzzy_minimum_error",
+            ThresholdMethod::Otsu => "otsu",
+            ThresholdMethod::Huang => "huang",
+            ThresholdMethod::Intermodes => "intermodes",
+            ThresholdMethod::IsoData => "isodata",
+            ThresholdMethod::Li => "li",
+            ThresholdMethod::MaxEntropy => "max_entropy",
+            ThresholdMethod::Mean => "mean",
+            ThresholdMethod::Minimum => "minimum",
+            ThresholdMethod::Moments => "moments",
+            ThresholdMethod::RenyiEntropy => "renyi_entropy",
+            ThresholdMethod::Shanbhag => "shanbhag",
+            ThresholdMethod::Triangle => "triangle",
+            ThresholdMethod::Yen => "yen",
+        }
+    }
+
+    pub fn compute(&self, histogram: &[u32; 256]) -> ThresholdSelection {
+        match self {
+            ThresholdMethod::PercentBlack(percent) => {
+                compute_percent_black_threshold(histogram, *percent)
+            }
+            ThresholdMethod::MeanIterative => compute_mean_iterative_threshold(histogram),
+            ThresholdMethod::Entropy => compute_entropy_threshold(histogram),
+            ThresholdMethod::MinimumError => compute_minimum_error_threshold(histogram),
+            ThresholdMethod::FuzzyMinimumError => compute_fuzzy_minimum_error_threshold(histogram),
+            ThresholdMethod::Otsu => compute_otsu_threshold(histogram),
+            ThresholdMethod::Huang => compute_huang_threshold(histogram),
+            ThresholdMethod::Intermodes => compute_intermodes_threshold(histogram),
+            ThresholdMethod::IsoData => compute_isodata_threshold(histogram),
+            ThresholdMethod::Li => compute_li_threshold(histogram),
+            ThresholdMethod::MaxEntropy => compute_max_entropy_threshold(histogram),
+            ThresholdMethod::Mean => compute_mean_threshold(histogram),
+            ThresholdMethod::Minimum => compute_minimum_threshold(histogram),
+            ThresholdMethod::Moments => compute_moments_threshold(histogram),
+            ThresholdMethod::RenyiEntropy => compute_renyi_entropy_threshold(histogram),
+            ThresholdMethod::Shanbhag => compute_shanbhag_threshold(histogram),
+            ThresholdMethod::Triangle => compute_triangle_threshold(histogram),
+            ThresholdMethod::Yen => compute_yen_threshold(histogram),
+        }
+    }
+}
+
+fn normalize(histogram: &[u32; 256]) -> [f32; 256] {
+    let total: u32 = histogram.iter().sum();
+
+    histogram.map(|x| x as f32 / total as f32)
+}
+
+/// Criterion is the fraction of pixels at or below each level.
+pub fn compute_percent_black_threshold(histogram: &[u32; 256], percent: f32) -> ThresholdSelection {
+    let total: u32 = histogram.iter().sum();
+    let pixels = (total as f32 * percent).floor() as u32;
+
+    let mut criterion = vec![0.0; 256];
+    let mut sum = 0;
+    let mut threshold = None;
+    for i in 0..256 {
+        sum += histogram[i];
+        criterion[i] = sum as f32 / total as f32;
+        if sum >= pixels && threshold.is_none() {
+            threshold = Some(i);
+        }
+    }
+
+    ThresholdSelection {
+        threshold: threshold.unwrap_or(0) as u8,
+        criterion,
+    }
+}
+
+/// Criterion is the midpoint of the class means minus the level, the method
+/// converges where it crosses zero.
+pub fn compute_mean_iterative_threshold(histogram: &[u32; 256]) -> ThresholdSelection {
+    let class_means = |threshold: f32| {
+        let mut low_mean = 0.0;
+        let mut low_count = 0;
+        let mut high_mean = 0.0;
+        let mut high_count = 0;
+
+        for (i, &count) in histogram.iter().enumerate() {
+            if (i as f32) < threshold {
+                low_mean += i as f32 * count as f32;
+                low_count += count;
+            } else {
+                high_mean += i as f32 * count as f32;
+                high_count += count;
+            }
+        }
+
+        (low_mean / low_count as f32, high_mean / high_count as f32)
+    };
+
+    let mut mean = 0.0;
+    let mut prev_mean = 0.0;
+    let mut count = 0;
+
+    for (i, &level_count) in histogram.iter().enumerate() {
+        mean += i as f32 * level_count as f32;
+        count += level_count;
+    }
+
+    mean /= count as f32;
+
+    while (mean - prev_mean).abs() > 0.01 {
+        let (low_mean, high_mean) = class_means(mean);
+
+        prev_mean = mean;
+        mean = (low_mean + high_mean) / 2.0;
+    }
+
+    let criterion = (0..256)
+        .map(|i| {
+            let (low_mean, high_mean) = class_means(i as f32);
+            (low_mean + high_mean) / 2.0 - i as f32
+        })
+        .collect();
+
+    ThresholdSelection {
+        threshold: mean as u8,
+        criterion,
+    }
+}
+
+/// Criterion is the entropy function `f`, maximized.
+pub fn compute_entropy_threshold(histogram: &[u32; 256]) -> ThresholdSelection {
+    let histogram = normalize(histogram);
+    let mut max_sum = f32::MIN;
+    let mut f;
+    let mut pt = 0.0;
+
+    let mut max_low = histogram[0];
+    let mut max_high;
+    let mut ht = 0.0;
+    let mut ht_total = 0.0;
+
+    for &p in &histogram {
+        if p > 0.0 {
+            ht_total -= p * p.log2();
+        }
+    }
+
+    let mut criterion = vec![f32::NAN; 256];
+    let mut threshold = 0;
+    for (i, &p) in histogram.iter().enumerate() {
+        pt += p;
+        max_low = max_low.max(p);
+        max_high = if i < 255 { histogram[i + 1] } else { p };
+
+        for &q in histogram.iter().skip(i + 2) {
+            if q > max_high {
+                max_high = q;
+            }
+        }
+
+        if p > 0.0 {
+            ht -= p * p.log2();
+        }
+
+        f = ht * pt.log2() / (ht_total * max_low.log2())
+            + (1.0 - ht / ht_total) * (1.0 - pt).log2() / max_high.log2();
+        if f.is_finite() {
+            criterion[i] = f;
+        }
+
+        if f > max_sum {
+            max_sum = f;
+            threshold = i;
+        }
+    }
+
+    ThresholdSelection {
+        threshold: threshold as u8,
+        criterion,
+    }
+}
+
+/// Criterion is the Kittler-Illingworth error `j`, minimized.
+pub fn compute_minimum_error_threshold(histogram: &[u32; 256]) -> ThresholdSelection {
+    let histogram = normalize(histogram);
+
+    let mut min_value = f32::MAX;
+    let mut j;
+    let mut p1 = 0.0;
+    let mut p2 = 0.0;
+    let mut s1;
+    let mut s2;
+    let mut fv;
+    let mut u1;
+    let mut u2;
+    let mut pi1 = 0.0;
+    let mut pi2 = 0.0;
+
+    for (i, &p) in histogram.iter().enumerate() {
+        p2 += p;
+        pi2 += i as f32 * p;
+    }
+
+    let mut criterion = vec![f32::NAN; 256];
+    let mut threshold = 0;
+    for (i, &p) in histogram.iter().enumerate() {
+        p1 += p;
+        p2 -= p;
+        pi1 += i as f32 * p;
+        pi2 -= i as f32 * p;
+
+        u1 = if p1 > 0.0 { pi1 / p1 } else { 0.0 };
+        u2 = if p2 > 0.0 { pi2 / p2 } else { 0.0 };
+
+        s1 = 0.0;
+        if p1 > 0.0 {
+            for (j, &q) in histogram.iter().enumerate().take(i + 1) {
+                fv = j as f32 - u1;
+                s1 += fv * fv * q;
+            }
+            s1 /= p1;
+        }
+
+        s2 = 0.0;
+        if p2 > 0.0 {
+            for (j, &q) in histogram.iter().enumerate().skip(i + 1) {
+                fv = j as f32 - u2;
+                s2 += fv * fv * q;
+            }
+            s2 /= p2;
+        }
+
+        j = 1.0 + 2.0 * ((p1 * s1.log2() - p1.log2()) + p2 * (s2.log2() - p2.log2()));
+        if j.is_nan() || j == f32::NEG_INFINITY {
+            continue;
+        }
+        if j.is_finite() {
+            criterion[i] = j;
+        }
+
+        if j < min_value {
+            min_value = j;
+            threshold = i;
+        }
+    }
+
+    ThresholdSelection {
+        threshold: threshold as u8,
+        criterion,
+    }
+}
+
+/// Criterion is the fuzzy error `e` based on Shannon's entropy, minimized.
+pub fn compute_fuzzy_minimum_error_threshold(histogram: &[u32; 256]) -> ThresholdSelection {
+    let total: u32 = histogram.iter().sum();
+    let mut min_error = f32::MAX;
+    let mut threshold = 0;
+
+    let mut max = 0;
+    let mut min = 255;
+
+    for (i, &count) in histogram.iter().enumerate() {
+        if count > 0 {
+            if i > max {
+                max = i;
+            }
+            if i < min {
+                min = i;
+            }
+        }
+    }
+
+    let c = max.saturating_sub(min);
+
+    let mut criterion = vec![f32::NAN; 256];
+    for (t, value) in criterion.iter_mut().enumerate().take(255) {
+        let mut mu0 = 0.0;
+        let mut c0 = 0;
+        for (i, &count) in histogram.iter().enumerate().take(t + 1) {
+            mu0 += i as f32 * count as f32;
+            c0 += count;
+        }
+        mu0 /= c0 as f32;
+
+        let mut mu1 = 0.0;
+        let mut c1 = 0;
+        for (i, &count) in histogram.iter().enumerate().skip(t + 1) {
+            mu1 += i as f32 * count as f32;
+            c1 += count;
+        }
+        mu1 /= c1 as f32;
+
+        let mut e = 0.0;
+        for (i, &count) in histogram.iter().enumerate() {
+            let mu = if i <= t { mu0 } else { mu1 };
+            e += shannon(c as f32 / (c as f32 + (i as f32 - mu).abs())) * count as f32;
+        }
+
+        e /= total as f32;
+        if e.is_finite() {
+            *value = e;
+        }
+
+        if e < min_error {
+            min_error = e;
+            threshold = t;
+        }
+    }
+
+    ThresholdSelection {
+        threshold: threshold as u8,
+        criterion,
+    }
+}
+
+/// Criterion is the between-class variance when the level starts the upper
+/// class. The level itself is the single threshold of
+/// `compute_multi_otsu_thresholds`, so both agree on ties and on histograms
+/// with one populated bin.
+pub fn compute_otsu_threshold(histogram: &[u32; 256]) -> ThresholdSelection {
+    let threshold = compute_multi_otsu_thresholds(histogram, 1)[0];
+
+    let histogram = normalize(histogram);
+    let mean_total: f32 = (0..256).map(|i| i as f32 * histogram[i]).sum();
+    let mut criterion = vec![f32::NAN; 256];
+    let mut weight = 0.0;
+    let mut mean = 0.0;
+    for t in 1..256 {
+        weight += histogram[t - 1];
+        mean += (t - 1) as f32 * histogram[t - 1];
+        if weight <= 0.0 || weight >= 1.0 {
+            continue;
+        }
+
+        criterion[t] = (mean_total * weight - mean).powi(2) / (weight * (1.0 - weight));
+    }
+
+    ThresholdSelection {
+        threshold,
+        criterion,
+    }
+}
+
+/// Splits the histogram into `count + 1` classes maximizing the between-class
+/// variance. Each returned level is the lowest gray level of the class above
+/// it, in ascending order.
+pub fn compute_multi_otsu_thresholds(histogram: &[u32; 256], count: usize) -> Vec<u8> {
+    let classes = count.clamp(1, 255) + 1;
+
+    let mut weight = [0.0f64; 257];
+    let mut moment = [0.0f64; 257];
+    for i in 0..256 {
+        weight[i + 1] = weight[i] + histogram[i] as f64;
+        moment[i + 1] = moment[i] + i as f64 * histogram[i] as f64;
+    }
+
+    // Maximizing the sum of moment^2 / weight over the classes is
+    // equivalent to maximizing the between-class variance.
+    let score = |from: usize, to: usize| -> f64 {
+        let w = weight[to] - weight[from];
+        if w > 0.0 {
+            let m = moment[to] - moment[from];
+            m * m / w
+        } else {
+            0.0
+        }
+    };
+
+    // best[c][end] is the best score of splitting levels 0..end into c + 1
+    // classes, split[c][end] the start of the last of those classes.
+    let mut best = vec![[f64::MIN; 257]; classes];
+    let mut split = vec![[0usize; 257]; classes];
+    for (end, value) in best[0].iter_mut().enumerate().skip(1) {
+        *value = score(0, end);
+    }
+    for c in 1..classes {
+        for end in c + 1..=256 {
+            for start in c..end {
+                let value = best[c - 1][start] + score(start, end);
+                if value > best[c][end] {
+                    best[c][end] = value;
+                    split[c][end] = start;
+                }
+            }
+        }
+    }
+
+    let mut thresholds = vec![0u8; classes - 1];
+    let mut end = 256;
+    for c in (1..classes).rev() {
+        end = split[c][end];
+        thresholds[c - 1] = end as u8;
+    }
+
+    thresholds
+}
+
+fn shannon(x: f32) -> f32 {
+    if x == 0.0 {
+        0.0
+    } else {
+        -x * x.log2() - (1.0 - x) * (1.0 - x).log2()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Histogram with spikes, gaps and a slope, so ties between splits and
+    /// empty classes both come up.
+    fn histogram(seed: usize) -> [u32; 256] {
+        std::array::from_fn(|i| {
+            let noise = (i * 7919 + seed * 104_729) % 97;
+            match (i + seed) % 5 {
+                0 => 0,
+                1 => (noise * 40) as u32,
+                _ => (noise + i / 4) as u32,
+            }
+        })
+    }
+
+    fn spikes(levels: &[(usize, u32)]) -> [u32; 256] {
+        let mut histogram = [0; 256];
+        for &(level, count) in levels {
+            histogram[level] = count;
+        }
+
+        histogram
+    }
+
+    /// Sum of moment^2 / weight over the classes starting at `thresholds`,
+    /// the quantity the dynamic program maximizes.
+    fn score(histogram: &[u32; 256], thresholds: &[usize]) -> f64 {
+        let bounds: Vec<usize> = std::iter::once(0)
+            .chain(thresholds.iter().copied())
+            .chain(std::iter::once(256))
+            .collect();
+
+        bounds
+            .windows(2)
+            .map(|class| {
+                let levels = class[0]..class[1];
+                let weight: f64 = levels.clone().map(|i| histogram[i] as f64).sum();
+                let moment: f64 = levels.map(|i| i as f64 * histogram[i] as f64).sum();
+                if weight > 0.0 {
+                    moment * moment / weight
+                } else {
+                    0.0
+                }
+            })
+            .sum()
+    }
+
+    fn multi_otsu_score(histogram: &[u32; 256], count: usize) -> f64 {
+        let thresholds: Vec<usize> = compute_multi_otsu_thresholds(histogram, count)
+            .into_iter()
+            .map(usize::from)
+            .collect();
+
+        score(histogram, &thresholds)
+    }
+
+    #[test]
+    fn multi_otsu_splits_between_spikes() {
+        let histogram = spikes(&[(20, 50), (200, 30)]);
+        assert_eq!(compute_multi_otsu_thresholds(&histogram, 1), vec![21]);
+
+        let histogram = spikes(&[(10, 40), (100, 40), (220, 40)]);
+        assert_eq!(compute_multi_otsu_thresholds(&histogram, 2), vec![11, 101]);
+    }
+
+    #[test]
+    fn multi_otsu_matches_exhaustive_search() {
+        for seed in 0..4 {
+            let histogram = histogram(seed);
+
+            let best = (1..256)
+                .map(|t| score(&histogram, &[t]))
+                .fold(f64::MIN, f64::max);
+            let found = multi_otsu_score(&histogram, 1);
+            assert!((found - best).abs() <= best * 1e-12, "seed {}", seed);
+
+            let best = (1..256)
+                .flat_map(|t1| (t1 + 1..256).map(move |t2| [t1, t2]))
+                .map(|thresholds| score(&histogram, &thresholds))
+                .fold(f64::MIN, f64::max);
+            let found = multi_otsu_score(&histogram, 2);
+            assert!((found - best).abs() <= best * 1e-12, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn otsu_agrees_with_multi_otsu() {
+        let histograms = [
+            histogram(0),
+            histogram(3),
+            spikes(&[(20, 50), (200, 30)]),
+            spikes(&[(0, 10)]),
+            spikes(&[(128, 10)]),
+            spikes(&[(255, 10)]),
+        ];
+        for histogram in &histograms {
+            let selection = compute_otsu_threshold(histogram);
+            assert_eq!(
+                vec![selection.threshold],
+                compute_multi_otsu_thresholds(histogram, 1)
+            );
+
+            // The chosen level maximizes the plotted between-class variance,
+            // up to the precision of the f32 curve.
+            let best = selection
+                .criterion
+                .iter()
+                .copied()
+                .filter(|value| value.is_finite())
+                .fold(f32::NAN, f32::max);
+            if best.is_finite() {
+                assert!(selection.criterion[selection.threshold as usize] >= best * (1.0 - 1e-5));
+            }
+        }
+    }
+
+    #[test]
+    fn otsu_on_a_single_populated_bin() {
+        assert_eq!(compute_otsu_threshold(&spikes(&[(0, 10)])).threshold, 1);
+        assert_eq!(compute_otsu_threshold(&spikes(&[(255, 10)])).threshold, 1);
+    }
+
+    #[test]
+    fn multi_otsu_thresholds_ascend() {
+        for count in 1..=6 {
+            let thresholds = compute_multi_otsu_thresholds(&histogram(count), count);
+            assert_eq!(thresholds.len(), count);
+            assert!(thresholds.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
+}
@@ -0,0 +1,622 @@
+//! Histogram-based methods following ImageJ's `AutoThresholder`. ImageJ
+//! reports the last level of the lower class, here it is shifted to the first
+//! level of the upper class like the other selections.
+
+use super::ThresholdSelection;
+
+fn normalize(histogram: &[u32; 256]) -> [f64; 256] {
+    let total: u32 = histogram.iter().sum();
+
+    histogram.map(|x| x as f64 / total.max(1) as f64)
+}
+
+fn to_criterion(values: impl IntoIterator<Item = f64>) -> Vec<f32> {
+    values
+        .into_iter()
+        .map(|value| {
+            if value.is_finite() {
+                value as f32
+            } else {
+                f32::NAN
+            }
+        })
+        .collect()
+}
+
+/// Builds the selection from the last level of the lower class and the
+/// criterion indexed the same way.
+fn selection(last_lower: usize, criterion: Vec<f64>) -> ThresholdSelection {
+    ThresholdSelection {
+        threshold: (last_lower + 1).min(255) as u8,
+        criterion: to_criterion(std::iter::once(f64::NAN).chain(criterion.into_iter().take(255))),
+    }
+}
+
+/// Result of a method that found no threshold.
+fn no_selection() -> ThresholdSelection {
+    ThresholdSelection {
+        threshold: 0,
+        criterion: vec![f32::NAN; 256],
+    }
+}
+
+/// Like `selection`, for methods whose criterion is the smoothed histogram
+/// itself and so stays aligned with the gray levels.
+fn smoothed_selection(last_lower: usize, smoothed: &[f64; 256]) -> ThresholdSelection {
+    ThresholdSelection {
+        threshold: (last_lower + 1).min(255) as u8,
+        criterion: to_criterion(smoothed.iter().copied()),
+    }
+}
+
+/// Cumulative probability of the lower and upper class together with the
+/// first and last level where both classes are non-empty.
+struct ClassProbabilities {
+    lower: [f64; 256],
+    upper: [f64; 256],
+    first: usize,
+    last: usize,
+}
+
+impl ClassProbabilities {
+    fn new(histogram: &[f64; 256]) -> Self {
+        let mut lower = [0.0; 256];
+        let mut upper = [0.0; 256];
+        lower[0] = histogram[0];
+        upper[0] = 1.0 - lower[0];
+        for i in 1..256 {
+            lower[i] = lower[i - 1] + histogram[i];
+            upper[i] = 1.0 - lower[i];
+        }
+
+        let first = (0..256)
+            .find(|&i| lower[i].abs() >= f64::EPSILON)
+            .unwrap_or(0);
+        let last = (first..256)
+            .rev()
+            .find(|&i| upper[i].abs() >= f64::EPSILON)
+            .unwrap_or(255);
+
+        Self {
+            lower,
+            upper,
+            first,
+            last,
+        }
+    }
+}
+
+/// Repeatedly smooths the histogram with a 3-point running mean until it has
+/// exactly two local maxima. Returns `None` if that never happens.
+fn smooth_until_bimodal(histogram: &[u32; 256]) -> Option<[f64; 256]> {
+    let mut smoothed = histogram.map(|x| x as f64);
+
+    for _ in 0..10000 {
+        if local_maxima(&smoothed).count() == 2 {
+            return Some(smoothed);
+        }
+
+        let (mut current, mut next) = (0.0, smoothed[0]);
+        for i in 0..255 {
+            let previous = current;
+            current = next;
+            next = smoothed[i + 1];
+            smoothed[i] = (previous + current + next) / 3.0;
+        }
+        smoothed[255] = (current + next) / 3.0;
+    }
+
+    None
+}
+
+fn local_maxima(histogram: &[f64; 256]) -> impl Iterator<Item = usize> + '_ {
+    (1..255).filter(|&i| histogram[i - 1] < histogram[i] && histogram[i + 1] < histogram[i])
+}
+
+/// Huang's fuzzy thresholding minimizing Shannon's entropy of the membership
+/// function. Criterion is that entropy, minimized.
+pub fn compute_huang_threshold(histogram: &[u32; 256]) -> ThresholdSelection {
+    let mut criterion = vec![f64::NAN; 256];
+    let first = match histogram.iter().position(|&x| x > 0) {
+        Some(first) => first,
+        None => return selection(0, criterion),
+    };
+    let last = histogram.iter().rposition(|&x| x > 0).unwrap();
+    if first == last {
+        return selection(first, criterion);
+    }
+
+    let mut count = [0.0f64; 256];
+    let mut moment = [0.0f64; 256];
+    count[0] = histogram[0] as f64;
+    for i in first.max(1)..=last {
+        count[i] = count[i - 1] + histogram[i] as f64;
+        moment[i] = moment[i - 1] + i as f64 * histogram[i] as f64;
+    }
+
+    // Entropy summands for every distance from the class mean.
+    let c = (last - first) as f64;
+    let summands: Vec<f64> = (0..=last - first)
+        .map(|i| {
+            if i == 0 {
+                return 0.0;
+            }
+            let mu = 1.0 / (1.0 + i as f64 / c);
+            -mu * mu.ln() - (1.0 - mu) * (1.0 - mu).ln()
+        })
+        .collect();
+
+    let mut best_threshold = 0;
+    let mut best_entropy = f64::MAX;
+    for threshold in first..=last {
+        let mut entropy = 0.0;
+        let mu = (moment[threshold] / count[threshold]).round() as usize;
+        for i in first..=threshold {
+            entropy += summands[i.abs_diff(mu)] * histogram[i] as f64;
+        }
+        let upper = count[last] - count[threshold];
+        if upper > 0.0 {
+            let mu = ((moment[last] - moment[threshold]) / upper).round() as usize;
+            for i in threshold + 1..=last {
+                entropy += summands[i.abs_diff(mu)] * histogram[i] as f64;
+            }
+        }
+
+        criterion[threshold] = entropy;
+        if entropy < best_entropy {
+            best_entropy = entropy;
+            best_threshold = threshold;
+        }
+    }
+
+    selection(best_threshold, criterion)
+}
+
+/// Midpoint between the two peaks of the histogram smoothed until bimodal,
+/// or 0 if it never becomes bimodal. Criterion is the smoothed histogram.
+pub fn compute_intermodes_threshold(histogram: &[u32; 256]) -> ThresholdSelection {
+    match smooth_until_bimodal(histogram) {
+        Some(smoothed) => {
+            let peaks: usize = local_maxima(&smoothed).sum();
+            smoothed_selection(peaks / 2, &smoothed)
+        }
+        None => no_selection(),
+    }
+}
+
+/// Ridler and Calvard's iterative intermeans starting from the first
+/// non-empty level, or 0 if it does not converge. Criterion is the midpoint
+/// of the class means minus the level, the method stops where it reaches
+/// zero.
+pub fn compute_isodata_threshold(histogram: &[u32; 256]) -> ThresholdSelection {
+    let class_means = |g: usize| {
+        let (mut low, mut low_count, mut high, mut high_count) = (0u64, 0u64, 0u64, 0u64);
+        for (i, &count) in histogram.iter().enumerate() {
+            if i <= g {
+                low += i as u64 * count as u64;
+                low_count += count as u64;
+            } else {
+                high += i as u64 * count as u64;
+                high_count += count as u64;
+            }
+        }
+
+        if low_count > 0 && high_count > 0 {
+            Some(((low / low_count) as f64, (high / high_count) as f64))
+        } else {
+            None
+        }
+    };
+
+    let criterion: Vec<f64> = (0..256)
+        .map(|g| match class_means(g) {
+            Some((low, high)) => (low + high) / 2.0 - g as f64,
+            None => f64::NAN,
+        })
+        .collect();
+
+    let mut g = (1..256).find(|&i| histogram[i] > 0).map_or(0, |i| i + 1);
+    while g < 255 {
+        if let Some((low, high)) = class_means(g) {
+            if g == ((low + high) / 2.0).round() as usize {
+                return selection(g, criterion);
+            }
+        }
+        g += 1;
+    }
+
+    ThresholdSelection {
+        threshold: 0,
+        ..selection(0, criterion)
+    }
+}
+
+/// Li's iterative minimum cross entropy. Criterion is the cross entropy
+/// between the image and its two-level segmentation, minimized.
+pub fn compute_li_threshold(histogram: &[u32; 256]) -> ThresholdSelection {
+    let class_means = |threshold: usize| {
+        let (mut back, mut back_count, mut object, mut object_count) = (0.0, 0.0, 0.0, 0.0);
+        for (i, &count) in histogram.iter().enumerate() {
+            if i <= threshold {
+                back += i as f64 * count as f64;
+                back_count += count as f64;
+            } else {
+                object += i as f64 * count as f64;
+                object_count += count as f64;
+            }
+        }
+
+        (
+            back,
+            if back_count > 0.0 {
+                back / back_count
+            } else {
+                0.0
+            },
+            object,
+            if object_count > 0.0 {
+                object / object_count
+            } else {
+                0.0
+            },
+        )
+    };
+
+    let criterion: Vec<f64> = (0..256)
+        .map(|threshold| {
+            let (back, back_mean, object, object_mean) = class_means(threshold);
+            -back * back_mean.ln() - object * object_mean.ln()
+        })
+        .collect();
+
+    let total: f64 = histogram.iter().map(|&x| x as f64).sum();
+    let mean = (0..256)
+        .map(|i| i as f64 * histogram[i] as f64)
+        .sum::<f64>()
+        / total;
+
+    let mut threshold = 0;
+    let mut new_threshold = mean;
+    for _ in 0..1000 {
+        let old_threshold = new_threshold;
+        threshold = ((old_threshold + 0.5) as usize).min(255);
+
+        let (_, back_mean, _, object_mean) = class_means(threshold);
+        let temp = (back_mean - object_mean) / (back_mean.ln() - object_mean.ln());
+        new_threshold = if temp < -f64::EPSILON {
+            (temp - 0.5) as i64 as f64
+        } else {
+            (temp + 0.5) as i64 as f64
+        };
+
+        if (new_threshold - old_threshold).abs() <= 0.5 {
+            break;
+        }
+    }
+
+    selection(threshold, criterion)
+}
+
+/// Kapur, Sahoo and Wong's maximum entropy. Criterion is the sum of the
+/// class entropies, maximized.
+pub fn compute_max_entropy_threshold(histogram: &[u32; 256]) -> ThresholdSelection {
+    let (threshold, criterion) = max_entropy(&normalize(histogram));
+
+    selection(threshold, criterion)
+}
+
+fn max_entropy(histogram: &[f64; 256]) -> (usize, Vec<f64>) {
+    let classes = ClassProbabilities::new(histogram);
+
+    let mut criterion = vec![f64::NAN; 256];
+    let mut threshold = 0;
+    let mut max_entropy = f64::MIN;
+    let levels = criterion.iter_mut().enumerate();
+    for (t, value) in levels.take(classes.last + 1).skip(classes.first) {
+        let mut entropy = 0.0;
+        for &level in &histogram[..=t] {
+            if level > 0.0 {
+                let p = level / classes.lower[t];
+                entropy -= p * p.ln();
+            }
+        }
+        for &level in &histogram[t + 1..] {
+            if level > 0.0 {
+                let p = level / classes.upper[t];
+                entropy -= p * p.ln();
+            }
+        }
+
+        *value = entropy;
+        if entropy > max_entropy {
+            max_entropy = entropy;
+            threshold = t;
+        }
+    }
+
+    (threshold, criterion)
+}
+
+/// Mean gray level. Has no criterion.
+pub fn compute_mean_threshold(histogram: &[u32; 256]) -> ThresholdSelection {
+    let total: f64 = histogram.iter().map(|&x| x as f64).sum();
+    let sum: f64 = (0..256).map(|i| i as f64 * histogram[i] as f64).sum();
+    let mean = if total > 0.0 { sum / total } else { 0.0 };
+
+    selection(mean.floor() as usize, vec![f64::NAN; 256])
+}
+
+/// Valley between the two peaks of the histogram smoothed until bimodal, or
+/// 0 if it never becomes bimodal. Criterion is the smoothed histogram.
+pub fn compute_minimum_threshold(histogram: &[u32; 256]) -> ThresholdSelection {
+    match smooth_until_bimodal(histogram) {
+        Some(smoothed) => {
+            let threshold = (1..255)
+                .find(|&i| smoothed[i - 1] > smoothed[i] && smoothed[i + 1] >= smoothed[i])
+                .unwrap_or(0);
+            smoothed_selection(threshold, &smoothed)
+        }
+        None => no_selection(),
+    }
+}
+
+/// Tsai's moment-preserving thresholding. Criterion is the fraction of
+/// pixels at or below each level, the threshold is where it passes the
+/// fraction preserving the first three moments.
+pub fn compute_moments_threshold(histogram: &[u32; 256]) -> ThresholdSelection {
+    let histogram = normalize(histogram);
+    let (m0, mut m1, mut m2, mut m3) = (1.0, 0.0, 0.0, 0.0);
+    for (i, &p) in histogram.iter().enumerate() {
+        let level = i as f64;
+        m1 += level * p;
+        m2 += level * level * p;
+        m3 += level * level * level * p;
+    }
+
+    let cd = m0 * m2 - m1 * m1;
+    let c0 = (-m2 * m2 + m1 * m3) / cd;
+    let c1 = (m0 * -m3 + m2 * m1) / cd;
+    let z0 = 0.5 * (-c1 - (c1 * c1 - 4.0 * c0).sqrt());
+    let z1 = 0.5 * (-c1 + (c1 * c1 - 4.0 * c0).sqrt());
+    let p0 = (z1 - m1) / (z1 - z0);
+
+    let mut criterion = vec![0.0; 256];
+    let mut sum = 0.0;
+    let mut threshold = None;
+    for i in 0..256 {
+        sum += histogram[i];
+        criterion[i] = sum;
+        if sum > p0 && threshold.is_none() {
+            threshold = Some(i);
+        }
+    }
+
+    selection(threshold.unwrap_or(0), criterion)
+}
+
+/// Kapur's method generalized to Renyi's entropy, combining the thresholds
+/// for orders 0.5, 1 and 2. Criterion is the order 0.5 entropy, maximized.
+pub fn compute_renyi_entropy_threshold(histogram: &[u32; 256]) -> ThresholdSelection {
+    let histogram = normalize(histogram);
+    let classes = ClassProbabilities::new(&histogram);
+    let (kapur_threshold, _) = max_entropy(&histogram);
+
+    let renyi = |alpha: f64| {
+        let term = 1.0 / (1.0 - alpha);
+        let mut criterion = vec![f64::NAN; 256];
+        let mut threshold = 0;
+        let mut max_entropy = 0.0;
+        let levels = criterion.iter_mut().enumerate();
+        for (t, value) in levels.take(classes.last + 1).skip(classes.first) {
+            let back: f64 = (0..=t)
+                .map(|i| (histogram[i] / classes.lower[t]).powf(alpha))
+                .sum();
+            let object: f64 = (t + 1..256)
+                .map(|i| (histogram[i] / classes.upper[t]).powf(alpha))
+                .sum();
+
+            let product = back * object;
+            let entropy = term * if product > 0.0 { product.ln() } else { 0.0 };
+            *value = entropy;
+            if entropy > max_entropy {
+                max_entropy = entropy;
+                threshold = t;
+            }
+        }
+
+        (threshold, criterion)
+    };
+    let (low_threshold, criterion) = renyi(0.5);
+    let (high_threshold, _) = renyi(2.0);
+
+    let mut t = [low_threshold, kapur_threshold, high_threshold];
+    t.sort_unstable();
+
+    let (beta1, beta2, beta3) = match (t[1] - t[0] <= 5, t[2] - t[1] <= 5) {
+        (true, true) => (1.0, 2.0, 1.0),
+        (true, false) => (0.0, 1.0, 3.0),
+        (false, true) => (3.0, 1.0, 0.0),
+        (false, false) => (1.0, 2.0, 1.0),
+    };
+    let omega = classes.lower[t[2]] - classes.lower[t[0]];
+    let threshold = t[0] as f64 * (classes.lower[t[0]] + 0.25 * omega * beta1)
+        + 0.25 * t[1] as f64 * omega * beta2
+        + t[2] as f64 * (classes.upper[t[2]] + 0.25 * omega * beta3);
+
+    selection(threshold as usize, criterion)
+}
+
+/// Shanbhag's fuzzy entropy. Criterion is the difference between the class
+/// information measures, minimized.
+pub fn compute_shanbhag_threshold(histogram: &[u32; 256]) -> ThresholdSelection {
+    let histogram = normalize(histogram);
+    let classes = ClassProbabilities::new(&histogram);
+
+    let mut criterion = vec![f64::NAN; 256];
+    let mut threshold = 0;
+    let mut min_entropy = f64::MAX;
+    let levels = criterion.iter_mut().enumerate();
+    for (t, value) in levels.take(classes.last + 1).skip(classes.first) {
+        let term = 0.5 / classes.lower[t];
+        let mut back = 0.0;
+        for (i, &p) in histogram.iter().enumerate().take(t + 1).skip(1) {
+            back -= p * (1.0 - term * classes.lower[i - 1]).ln();
+        }
+        back *= term;
+
+        let term = 0.5 / classes.upper[t];
+        let mut object = 0.0;
+        for (i, &p) in histogram.iter().enumerate().skip(t + 1) {
+            object -= p * (1.0 - term * classes.upper[i]).ln();
+        }
+        object *= term;
+
+        let entropy = (back - object).abs();
+        *value = entropy;
+        if entropy < min_entropy {
+            min_entropy = entropy;
+            threshold = t;
+        }
+    }
+
+    selection(threshold, criterion)
+}
+
+/// Zack's triangle method: the level farthest from the line joining the
+/// histogram peak and the far end of its longer tail. Criterion is that
+/// distance.
+pub fn compute_triangle_threshold(histogram: &[u32; 256]) -> ThresholdSelection {
+    let mut criterion = vec![f64::NAN; 256];
+    let first = match histogram.iter().position(|&x| x > 0) {
+        Some(first) => first,
+        None => return selection(0, criterion),
+    };
+    let last = histogram.iter().rposition(|&x| x > 0).unwrap();
+    // Anchor the line at the empty bins just outside the data.
+    let min = first.saturating_sub(1);
+    let max_end = (last + 1).min(255);
+    let peak = (0..256).fold(0, |peak, i| {
+        if histogram[i] > histogram[peak] {
+            i
+        } else {
+            peak
+        }
+    });
+
+    // Work on the longer tail, mirroring the histogram if it is on the right.
+    let inverted = peak - min < max_end - peak;
+    let data: Vec<f64> = if inverted {
+        histogram.iter().rev().map(|&x| x as f64).collect()
+    } else {
+        histogram.iter().map(|&x| x as f64).collect()
+    };
+    let (min, peak) = if inverted {
+        (255 - max_end, 255 - peak)
+    } else {
+        (min, peak)
+    };
+    if min == peak {
+        return selection(if inverted { 255 - min } else { min }, criterion);
+    }
+
+    let (mut nx, mut ny) = (data[peak], min as f64 - peak as f64);
+    let length = (nx * nx + ny * ny).sqrt();
+    nx /= length;
+    ny /= length;
+    let offset = nx * min as f64 + ny * data[min];
+
+    let mut split = min;
+    let mut split_distance = 0.0;
+    for i in min + 1..=peak {
+        let distance = nx * i as f64 + ny * data[i] - offset;
+        criterion[if inverted { 255 - i } else { i }] = distance;
+        if distance > split_distance {
+            split = i;
+            split_distance = distance;
+        }
+    }
+    let split = split.saturating_sub(1);
+
+    selection(if inverted { 255 - split } else { split }, criterion)
+}
+
+/// Yen's maximum correlation criterion, maximized.
+pub fn compute_yen_threshold(histogram: &[u32; 256]) -> ThresholdSelection {
+    let histogram = normalize(histogram);
+
+    let mut lower = [0.0; 256];
+    let mut lower_squared = [0.0; 256];
+    let mut upper_squared = [0.0; 256];
+    lower[0] = histogram[0];
+    lower_squared[0] = histogram[0] * histogram[0];
+    for i in 1..256 {
+        lower[i] = lower[i - 1] + histogram[i];
+        lower_squared[i] = lower_squared[i - 1] + histogram[i] * histogram[i];
+    }
+    for i in (0..255).rev() {
+        upper_squared[i] = upper_squared[i + 1] + histogram[i + 1] * histogram[i + 1];
+    }
+
+    let log_or_zero = |x: f64| if x > 0.0 { x.ln() } else { 0.0 };
+    let mut criterion = vec![f64::NAN; 256];
+    let mut threshold = 0;
+    let mut max_correlation = f64::MIN;
+    for t in 0..256 {
+        let correlation = -log_or_zero(lower_squared[t] * upper_squared[t])
+            + 2.0 * log_or_zero(lower[t] * (1.0 - lower[t]));
+        criterion[t] = correlation;
+        if correlation > max_correlation {
+            max_correlation = correlation;
+            threshold = t;
+        }
+    }
+
+    selection(threshold, criterion)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 50 pixels at level 20 and 30 at level 200.
+    fn two_spikes() -> [u32; 256] {
+        let mut histogram = [0; 256];
+        histogram[20] = 50;
+        histogram[200] = 30;
+
+        histogram
+    }
+
+    // ImageJ's results on `two_spikes`, worked out from its formulas, are one
+    // lower since it reports the last level of the lower class.
+
+    #[test]
+    fn mean_is_the_floor_of_the_mean_level() {
+        // 7000 / 80 = 87.5
+        assert_eq!(compute_mean_threshold(&two_spikes()).threshold, 88);
+    }
+
+    #[test]
+    fn isodata_stops_at_the_midpoint_of_the_class_means() {
+        // (20 + 200) / 2
+        assert_eq!(compute_isodata_threshold(&two_spikes()).threshold, 111);
+    }
+
+    #[test]
+    fn intermodes_takes_the_midpoint_of_the_peaks() {
+        // Already bimodal, no smoothing: (20 + 200) / 2
+        assert_eq!(compute_intermodes_threshold(&two_spikes()).threshold, 111);
+    }
+
+    #[test]
+    fn minimum_takes_the_first_level_past_a_peak() {
+        // 21, where the histogram stops falling after the first peak
+        assert_eq!(compute_minimum_threshold(&two_spikes()).threshold, 22);
+    }
+
+    #[test]
+    fn li_converges_to_the_logarithmic_mean() {
+        // (200 - 20) / (ln 200 - ln 20) = 78.17, rounded
+        assert_eq!(compute_li_threshold(&two_spikes()).threshold, 79);
+    }
+}
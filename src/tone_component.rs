@@ -0,0 +1,292 @@
+use binhis::{
+    image::{Channel, Curve, Intensity, Levels},
+    pipeline::Operation,
+};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{CanvasRenderingContext2d, Event, HtmlCanvasElement, HtmlInputElement, MouseEvent};
+use yew::{html, Callback, Component, NodeRef, Properties};
+
+use crate::shared_image::SharedImage;
+
+/// One pixel per level in both directions.
+const CANVAS_SIZE: u32 = 256;
+/// How close in pixels a click has to be to grab a control point.
+const GRAB_RADIUS: f64 = 6.0;
+const LEVEL_FIELDS: [&str; 5] = [
+    "input black",
+    "input white",
+    "gamma",
+    "output black",
+    "output white",
+];
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub image: SharedImage,
+    pub intensity: Intensity,
+    pub on_apply: Callback<Operation>,
+}
+
+pub enum Msg {
+    LevelChanged(usize, Event),
+    ApplyLevels,
+    ResetLevels,
+    CurvePressed(MouseEvent),
+    CurveDragged(MouseEvent),
+    CurveReleased,
+    PointRemoved(MouseEvent),
+    ApplyCurve,
+    ResetCurve,
+}
+
+/// Levels dialog and curves editor. Control points are added by clicking the
+/// curve canvas, dragged to move them and removed by double clicking.
+pub struct ToneComponent {
+    levels: Levels,
+    curve: Curve,
+    dragged: Option<usize>,
+    histogram: [u32; 256],
+    /// Image and intensity the histogram was taken from.
+    source: (SharedImage, Intensity),
+    canvas_ref: NodeRef,
+}
+
+impl ToneComponent {
+    /// Channel the histogram behind the curve is taken from.
+    fn channel(intensity: Intensity) -> Channel {
+        match intensity {
+            Intensity::PerChannel => Channel::Luminance,
+            Intensity::Luma => Channel::Luma,
+            Intensity::LabLightness => Channel::LabLightness,
+            Intensity::Value => Channel::Value,
+        }
+    }
+
+    fn level_values(&self) -> [f32; 5] {
+        [
+            self.levels.input.0 as f32,
+            self.levels.input.1 as f32,
+            self.levels.gamma,
+            self.levels.output.0 as f32,
+            self.levels.output.1 as f32,
+        ]
+    }
+
+    /// Level under the mouse, input along x and output along y.
+    fn position(event: &MouseEvent) -> (u8, u8) {
+        let level = |offset: i32| offset.clamp(0, 255) as u8;
+
+        (level(event.offset_x()), level(255 - event.offset_y()))
+    }
+
+    fn point_at(&self, (x, y): (u8, u8)) -> Option<usize> {
+        self.curve.points().iter().position(|point| {
+            let dx = point.0 as f64 - x as f64;
+            let dy = point.1 as f64 - y as f64;
+            dx.hypot(dy) <= GRAB_RADIUS
+        })
+    }
+
+    fn draw(&self) {
+        let canvas_ctx = match self.canvas_ref.cast::<HtmlCanvasElement>() {
+            Some(canvas) => canvas
+                .get_context("2d")
+                .unwrap()
+                .unwrap()
+                .dyn_into::<CanvasRenderingContext2d>()
+                .unwrap(),
+            None => return,
+        };
+
+        let size = CANVAS_SIZE as f64;
+        canvas_ctx.set_fill_style(&JsValue::from_str("#ffffff"));
+        canvas_ctx.fill_rect(0.0, 0.0, size, size);
+
+        let max = self.histogram.iter().copied().max().unwrap_or(0).max(1) as f64;
+        canvas_ctx.set_fill_style(&JsValue::from_str("#d0d0d0"));
+        for (i, count) in self.histogram.iter().enumerate() {
+            let bar = *count as f64 / max * size;
+            canvas_ctx.fill_rect(i as f64, size - bar, 1.0, bar);
+        }
+
+        canvas_ctx.set_stroke_style(&JsValue::from_str("#a0a0a0"));
+        canvas_ctx.begin_path();
+        canvas_ctx.move_to(0.0, size);
+        canvas_ctx.line_to(size, 0.0);
+        canvas_ctx.stroke();
+
+        let y = |level: u8| size - 1.0 - level as f64;
+        canvas_ctx.set_stroke_style(&JsValue::from_str("#0050c8"));
+        canvas_ctx.begin_path();
+        for (i, level) in self.curve.lut().iter().enumerate() {
+            if i == 0 {
+                canvas_ctx.move_to(i as f64, y(*level));
+            } else {
+                canvas_ctx.line_to(i as f64, y(*level));
+            }
+        }
+        canvas_ctx.stroke();
+
+        canvas_ctx.set_fill_style(&JsValue::from_str("#e00000"));
+        for (x, level) in self.curve.points() {
+            canvas_ctx.fill_rect(*x as f64 - 3.0, y(*level) - 3.0, 6.0, 6.0);
+        }
+    }
+}
+
+impl Component for ToneComponent {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &yew::Context<Self>) -> Self {
+        let props = ctx.props();
+
+        Self {
+            levels: Levels::default(),
+            curve: Curve::default(),
+            dragged: None,
+            histogram: props
+                .image
+                .get_channel_histogram(Self::channel(props.intensity)),
+            source: (props.image.clone(), props.intensity),
+            canvas_ref: NodeRef::default(),
+        }
+    }
+
+    fn changed(&mut self, ctx: &yew::Context<Self>) -> bool {
+        // The apply callback is new on every render of the parent, only take
+        // the histogram again when its source changed.
+        let props = ctx.props();
+        let source = (props.image.clone(), props.intensity);
+        if source == self.source {
+            return false;
+        }
+        self.histogram = props
+            .image
+            .get_channel_histogram(Self::channel(props.intensity));
+        self.source = source;
+
+        true
+    }
+
+    fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let link = ctx.link();
+
+        html! {
+            <details>
+                <summary>{"Levels and curves"}</summary>
+                <div>
+                    { for self.level_values().into_iter().enumerate().map(|(i, value)| {
+                        let (min, max, step) = if i == 2 { ("0.1", "10", "0.05") } else { ("0", "255", "1") };
+                        html! {
+                            <label>{format!("{} ", LEVEL_FIELDS[i])}
+                                <input type="number" {min} {max} {step} value={value.to_string()}
+                                    onchange={link.callback(move |event: Event| Msg::LevelChanged(i, event))} />
+                            </label>
+                        }
+                    }) }
+                    <button onclick={link.callback(|_| Msg::ApplyLevels)}>{"Apply levels"}</button>
+                    <button onclick={link.callback(|_| Msg::ResetLevels)}>{"Reset"}</button>
+                </div>
+                <div style="display: flex; align-items: flex-start; gap: 16px;">
+                    <canvas ref={self.canvas_ref.clone()}
+                        width={CANVAS_SIZE.to_string()}
+                        height={CANVAS_SIZE.to_string()}
+                        style="cursor: crosshair;"
+                        onmousedown={link.callback(Msg::CurvePressed)}
+                        onmousemove={link.callback(Msg::CurveDragged)}
+                        onmouseup={link.callback(|_| Msg::CurveReleased)}
+                        onmouseleave={link.callback(|_| Msg::CurveReleased)}
+                        ondblclick={link.callback(Msg::PointRemoved)}
+                    />
+                    <div>
+                        <div>{format!("Points: {}", self.curve)}</div>
+                        <button onclick={link.callback(|_| Msg::ApplyCurve)}>{"Apply curve"}</button>
+                        <button onclick={link.callback(|_| Msg::ResetCurve)}>{"Reset"}</button>
+                    </div>
+                </div>
+            </details>
+        }
+    }
+
+    fn update(&mut self, ctx: &yew::Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::LevelChanged(index, event) => {
+                let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+                let value = input.value_as_number() as f32;
+                if !value.is_finite() {
+                    return true;
+                }
+                let level = value.round().clamp(0.0, 255.0) as u8;
+                match index {
+                    0 => self.levels.input.0 = level.min(self.levels.input.1.saturating_sub(1)),
+                    1 => self.levels.input.1 = level.max(self.levels.input.0.saturating_add(1)),
+                    2 => self.levels.gamma = value.clamp(0.1, 10.0),
+                    3 => self.levels.output.0 = level,
+                    _ => self.levels.output.1 = level,
+                }
+
+                true
+            }
+            Msg::ApplyLevels => {
+                ctx.props().on_apply.emit(Operation::Levels {
+                    levels: self.levels,
+                    intensity: ctx.props().intensity,
+                });
+
+                true
+            }
+            Msg::ResetLevels => {
+                self.levels = Levels::default();
+
+                true
+            }
+            Msg::CurvePressed(event) => {
+                let position = Self::position(&event);
+                self.dragged = Some(
+                    self.point_at(position)
+                        .unwrap_or_else(|| self.curve.insert(position)),
+                );
+
+                true
+            }
+            Msg::CurveDragged(event) => match self.dragged {
+                Some(index) => {
+                    self.curve.move_point(index, Self::position(&event));
+
+                    true
+                }
+                None => false,
+            },
+            Msg::CurveReleased => {
+                self.dragged = None;
+
+                false
+            }
+            Msg::PointRemoved(event) => {
+                if let Some(index) = self.point_at(Self::position(&event)) {
+                    self.curve.remove(index);
+                }
+
+                true
+            }
+            Msg::ApplyCurve => {
+                ctx.props().on_apply.emit(Operation::Curve {
+                    curve: self.curve.clone(),
+                    intensity: ctx.props().intensity,
+                });
+
+                true
+            }
+            Msg::ResetCurve => {
+                self.curve = Curve::default();
+
+                true
+            }
+        }
+    }
+
+    fn rendered(&mut self, _ctx: &yew::Context<Self>, _first_render: bool) {
+        self.draw();
+    }
+}
@@ -0,0 +1,34 @@
+use gloo_events::EventListener;
+use js_sys::Uint8Array;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, FileReader, HtmlInputElement};
+use yew::Callback;
+
+/// Reads the file picked in the input that fired `event`, emitting its bytes
+/// to `on_load` or calling `on_error` if reading fails. Returns false if no
+/// file was picked.
+pub fn upload(event: &Event, on_load: Callback<Vec<u8>>, on_error: Callback<()>) -> bool {
+    let target: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+    let file = match target.files().and_then(|files| files.get(0)) {
+        Some(file) => file,
+        None => return false,
+    };
+
+    let file_reader = FileReader::new().unwrap();
+    file_reader.read_as_array_buffer(&file).unwrap();
+    let listener = EventListener::new(&file_reader, "load", move |event| {
+        let target = event.target().unwrap();
+        let target: FileReader = target.dyn_into().unwrap();
+        let result = target.result().unwrap();
+        let array = Uint8Array::new(&result);
+
+        on_load.emit(array.to_vec());
+    });
+    listener.forget();
+    let listener = EventListener::new(&file_reader, "error", move |_| {
+        on_error.emit(());
+    });
+    listener.forget();
+
+    true
+}